@@ -0,0 +1,198 @@
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::http::WallhavenClient;
+use crate::search::TopRange;
+use crate::settings::{load_settings, save_settings, Settings};
+use crate::wallhaven::{api_url, sends_api_key};
+
+/// Fields of Settings that can be imported from the account, in display order.
+const IMPORT_FIELDS: [&str; 7] = [
+    "purity",
+    "categories",
+    "ratios",
+    "resolutions",
+    "top_range",
+    "tag_blacklist",
+    "user_blacklist",
+];
+
+/// The filter part of Wallhaven's `/settings` response.
+#[derive(Debug, Deserialize)]
+struct AccountSettings {
+    #[serde(default)]
+    purity: Vec<String>,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    resolutions: Vec<String>,
+    #[serde(default)]
+    aspect_ratios: Vec<String>,
+    #[serde(default)]
+    toplist_range: String,
+    #[serde(default)]
+    tag_blacklist: Vec<String>,
+    #[serde(default)]
+    user_blacklist: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountSettingsResponse {
+    data: AccountSettings,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettingChange {
+    pub field: &'static str,
+    pub current: Value,
+    pub incoming: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountImport {
+    /// Only fields whose imported value differs from the current one.
+    pub changes: Vec<SettingChange>,
+}
+
+/// Turns `["sfw", "nsfw"]` into `"101"` given the flag order.
+fn to_bits(values: &[String], order: [&str; 3]) -> Option<String> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(
+        order
+            .iter()
+            .map(|flag| if values.iter().any(|v| v == flag) { '1' } else { '0' })
+            .collect(),
+    )
+}
+
+fn non_empty(values: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Settings as they would look with every account filter applied.
+fn imported_settings(current: &Settings, account: &AccountSettings) -> Settings {
+    let mut settings = current.clone();
+    if let Some(purity) = to_bits(&account.purity, ["sfw", "sketchy", "nsfw"]) {
+        settings.purity = purity;
+    }
+    if let Some(categories) = to_bits(&account.categories, ["general", "anime", "people"]) {
+        settings.categories = categories;
+    }
+    settings.ratios = non_empty(&account.aspect_ratios).join(",");
+    let resolutions = non_empty(&account.resolutions);
+    settings.search_defaults.resolutions = Some(resolutions).filter(|r| !r.is_empty());
+    if let Ok(range) = serde_json::from_value::<TopRange>(json!(account.toplist_range)) {
+        settings.search_defaults.top_range = Some(range);
+    }
+    settings.tag_blacklist = non_empty(&account.tag_blacklist);
+    settings.user_blacklist = non_empty(&account.user_blacklist);
+    settings
+}
+
+fn field_value(settings: &Settings, field: &str) -> Value {
+    match field {
+        "purity" => json!(settings.purity),
+        "categories" => json!(settings.categories),
+        "ratios" => json!(settings.ratios),
+        "resolutions" => json!(settings.search_defaults.resolutions),
+        "top_range" => json!(settings.search_defaults.top_range),
+        "tag_blacklist" => json!(settings.tag_blacklist),
+        "user_blacklist" => json!(settings.user_blacklist),
+        _ => Value::Null,
+    }
+}
+
+fn copy_field(target: &mut Settings, source: &Settings, field: &str) {
+    match field {
+        "purity" => target.purity = source.purity.clone(),
+        "categories" => target.categories = source.categories.clone(),
+        "ratios" => target.ratios = source.ratios.clone(),
+        "resolutions" => target.search_defaults.resolutions = source.search_defaults.resolutions.clone(),
+        "top_range" => target.search_defaults.top_range = source.search_defaults.top_range,
+        "tag_blacklist" => target.tag_blacklist = source.tag_blacklist.clone(),
+        "user_blacklist" => target.user_blacklist = source.user_blacklist.clone(),
+        _ => {}
+    }
+}
+
+async fn fetch_account_settings(
+    http: &WallhavenClient,
+    settings: &Settings,
+) -> Result<AccountSettings, AppError> {
+    let api_key = settings.api_key.trim();
+    if api_key.is_empty() {
+        return Err(AppError::invalid_input("API key not configured. Set it in Settings."));
+    }
+    let url = api_url(settings, "settings");
+    if !sends_api_key(&url) {
+        return Err(AppError::invalid_input(
+            "API keys are only sent to wallhaven.cc, not the configured API URL",
+        ));
+    }
+    let req = http.http().get(&url).header("X-API-Key", api_key);
+    let response = http.send("fetch_account_settings", req).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        warn!("fetch_account_settings: API returned status={}", status);
+        return Err(AppError::from_status(status));
+    }
+    let text = response
+        .text()
+        .await
+        .map_err(|e| AppError::from(e).context("reading body failed"))?;
+    let resp: AccountSettingsResponse = serde_json::from_str(&text)?;
+    Ok(resp.data)
+}
+
+/// Fetches the account's filters and reports how they differ from our Settings.
+#[tauri::command]
+pub async fn preview_account_settings(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+) -> Result<AccountImport, AppError> {
+    let current = load_settings(app);
+    let account = fetch_account_settings(&http, &current).await?;
+    let incoming = imported_settings(&current, &account);
+
+    let changes: Vec<SettingChange> = IMPORT_FIELDS
+        .iter()
+        .filter_map(|&field| {
+            let (current, incoming) = (field_value(&current, field), field_value(&incoming, field));
+            (current != incoming).then_some(SettingChange { field, current, incoming })
+        })
+        .collect();
+    debug!("preview_account_settings: {} fields differ", changes.len());
+    Ok(AccountImport { changes })
+}
+
+/// Re-fetches the account's filters and applies the accepted fields (all when `fields` is `None`).
+#[tauri::command]
+pub async fn apply_account_settings(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    fields: Option<Vec<String>>,
+) -> Result<Settings, AppError> {
+    let mut settings = load_settings(app.clone());
+    let account = fetch_account_settings(&http, &settings).await?;
+    let incoming = imported_settings(&settings, &account);
+
+    for field in IMPORT_FIELDS {
+        if fields.as_ref().is_none_or(|f| f.iter().any(|name| name == field)) {
+            copy_field(&mut settings, &incoming, field);
+        }
+    }
+    match &fields {
+        Some(f) => info!("apply_account_settings: applied {:?}", f),
+        None => info!("apply_account_settings: applied all fields"),
+    }
+    save_settings(app, settings.clone())?;
+    Ok(settings)
+}
@@ -0,0 +1,175 @@
+use chrono::{Datelike, Local};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::error::AppError;
+use crate::settings::{load_settings, Settings};
+
+/// Serializes updates to `bandwidth.json`; concurrent downloads each record
+/// their bytes when they finish.
+static USAGE_LOCK: Mutex<()> = Mutex::new(());
+
+const MB: u64 = 1024 * 1024;
+
+/// Bytes of full-size images downloaded in the current day and month
+/// (local time). Counters reset when the period rolls over.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Usage {
+    /// `YYYY-MM-DD`.
+    day: String,
+    day_bytes: u64,
+    /// `YYYY-MM`.
+    month: String,
+    month_bytes: u64,
+}
+
+impl Usage {
+    /// Drops counters that belong to a past day or month.
+    fn current(mut self) -> Self {
+        let now = Local::now();
+        let day = now.format("%Y-%m-%d").to_string();
+        let month = format!("{}-{:02}", now.year(), now.month());
+        if self.day != day {
+            self.day = day;
+            self.day_bytes = 0;
+        }
+        if self.month != month {
+            self.month = month;
+            self.month_bytes = 0;
+        }
+        self
+    }
+}
+
+fn usage_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .expect("failed to get config dir");
+    fs::create_dir_all(&dir).ok();
+    dir.join("bandwidth.json")
+}
+
+fn load_usage(app: &tauri::AppHandle) -> Usage {
+    fs::read_to_string(usage_path(app))
+        .ok()
+        .and_then(|s| serde_json::from_str::<Usage>(&s).ok())
+        .unwrap_or_default()
+        .current()
+}
+
+/// Adds `bytes` to today's and this month's totals.
+pub fn record(app: &tauri::AppHandle, bytes: u64) {
+    if bytes == 0 {
+        return;
+    }
+    let _lock = USAGE_LOCK.lock().unwrap();
+    let mut usage = load_usage(app);
+    usage.day_bytes += bytes;
+    usage.month_bytes += bytes;
+    let result = serde_json::to_string_pretty(&usage)
+        .map_err(AppError::from)
+        .and_then(|json| Ok(fs::write(usage_path(app), json)?));
+    match result {
+        Ok(()) => debug!("bandwidth: +{} bytes, {} today", bytes, usage.day_bytes),
+        Err(e) => warn!("bandwidth: couldn't save usage: {e}"),
+    }
+}
+
+/// Counts bytes as they stream in and records them when dropped, so
+/// failed and cancelled downloads are charged for what they used too.
+pub struct Meter<'a> {
+    app: &'a tauri::AppHandle,
+    bytes: u64,
+}
+
+impl<'a> Meter<'a> {
+    pub fn new(app: &'a tauri::AppHandle) -> Self {
+        Self { app, bytes: 0 }
+    }
+
+    pub fn add(&mut self, bytes: u64) {
+        self.bytes += bytes;
+    }
+}
+
+impl Drop for Meter<'_> {
+    fn drop(&mut self) {
+        record(self.app, self.bytes);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BandwidthUsage {
+    pub metered: bool,
+    pub day_bytes: u64,
+    pub month_bytes: u64,
+    /// 0 when unlimited.
+    pub daily_limit_bytes: u64,
+    /// 0 when unlimited.
+    pub monthly_limit_bytes: u64,
+    /// Bytes left before the tighter of the two limits, `None` when both are unlimited.
+    pub remaining_bytes: Option<u64>,
+    /// Metered mode prefers images at or below this size. 0 when unset.
+    pub max_file_bytes: u64,
+    /// True when a limit has been reached.
+    pub exhausted: bool,
+}
+
+fn usage_report(settings: &Settings, usage: &Usage) -> BandwidthUsage {
+    let daily = settings.bandwidth_daily_mb.saturating_mul(MB);
+    let monthly = settings.bandwidth_monthly_mb.saturating_mul(MB);
+    let remaining = [(daily, usage.day_bytes), (monthly, usage.month_bytes)]
+        .into_iter()
+        .filter(|&(limit, _)| limit > 0)
+        .map(|(limit, used)| limit.saturating_sub(used))
+        .min();
+    BandwidthUsage {
+        metered: settings.metered,
+        day_bytes: usage.day_bytes,
+        month_bytes: usage.month_bytes,
+        daily_limit_bytes: daily,
+        monthly_limit_bytes: monthly,
+        remaining_bytes: remaining,
+        max_file_bytes: settings.metered_max_file_mb.saturating_mul(MB),
+        exhausted: remaining == Some(0),
+    }
+}
+
+/// In metered mode, refuses a download of `file_size` bytes that the
+/// remaining budget can't cover. Outside metered mode usage is only tracked.
+pub fn check(app: &tauri::AppHandle, settings: &Settings, file_size: u64) -> Result<(), AppError> {
+    if !settings.metered {
+        return Ok(());
+    }
+    let report = usage_report(settings, &load_usage(app));
+    match report.remaining_bytes {
+        Some(remaining) if report.exhausted || file_size > remaining => {
+            info!("bandwidth: refusing {} byte download, {} left", file_size, remaining);
+            Err(AppError::budget_exceeded(format!(
+                "download budget spent ({} MB left, image is {} MB)",
+                remaining / MB,
+                file_size.div_ceil(MB)
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn bandwidth_usage(app: tauri::AppHandle) -> BandwidthUsage {
+    let settings = load_settings(app.clone());
+    usage_report(&settings, &load_usage(&app))
+}
+
+#[tauri::command]
+pub fn reset_bandwidth_usage(app: tauri::AppHandle) -> Result<(), AppError> {
+    let _lock = USAGE_LOCK.lock().unwrap();
+    let usage = Usage::default().current();
+    fs::write(usage_path(&app), serde_json::to_string_pretty(&usage)?)?;
+    info!("bandwidth: usage reset");
+    Ok(())
+}
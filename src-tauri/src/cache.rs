@@ -0,0 +1,470 @@
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::Manager;
+
+use crate::cacheindex::{self, CacheKey, IndexEntry, Snapshot};
+use crate::download::cache_dir;
+use crate::error::AppError;
+use crate::prefetch::Prefetcher;
+use crate::settings::{load_settings, Settings};
+use crate::wallhaven::Wallpaper;
+
+const IMAGE_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "gif", "webp"];
+
+/// A cached full-size image. `last_used` comes from the index's
+/// `last_access`, falling back to the file's mtime for unindexed files.
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_used: SystemTime,
+    pub index: Option<IndexEntry>,
+}
+
+/// Cached full-size images. In-progress `.part` files and anything that
+/// isn't an image (e.g. subdirectories) are left out.
+pub fn entries(app: &tauri::AppHandle) -> Result<Vec<CacheEntry>, AppError> {
+    let mut indexed: HashMap<String, IndexEntry> = cacheindex::entries(app)
+        .into_iter()
+        .map(|e| (e.filename.clone(), e))
+        .collect();
+    let mut entries = Vec::new();
+    for item in fs::read_dir(cache_dir(app)?)? {
+        let item = item?;
+        let path = item.path();
+        let is_image = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+        let meta = item.metadata()?;
+        if !is_image || !meta.is_file() {
+            continue;
+        }
+        let index = indexed.remove(&filename(&path));
+        let last_used = index
+            .as_ref()
+            .and_then(|e| chrono::DateTime::parse_from_rfc3339(&e.last_access).ok())
+            .map(SystemTime::from)
+            .unwrap_or_else(|| meta.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+        entries.push(CacheEntry {
+            path,
+            size: meta.len(),
+            last_used,
+            index,
+        });
+    }
+    Ok(entries)
+}
+
+/// Looks up the cached file for each `(id, url)` pair through the index.
+fn cached_paths<'a>(
+    index: &Snapshot,
+    items: impl Iterator<Item = (&'a str, &'a str)>,
+) -> HashSet<PathBuf> {
+    items
+        .filter_map(|(id, url)| index.lookup(&CacheKey::new(id, url)))
+        .collect()
+}
+
+/// Wallpapers of the cycled collection fetched this session, by id, so
+/// eviction keeps what the collection cycle comes back to. Managed as Tauri state.
+#[derive(Default)]
+pub struct CycleCollection(Mutex<(u64, HashMap<String, String>)>);
+
+impl CycleCollection {
+    /// Records a fetched page of `collection_id`, dropping what was recorded
+    /// for any other collection.
+    pub fn remember(&self, collection_id: u64, wallpapers: &[Wallpaper]) {
+        let mut seen = self.0.lock().unwrap();
+        if seen.0 != collection_id {
+            *seen = (collection_id, HashMap::new());
+        }
+        for wallpaper in wallpapers {
+            seen.1.insert(wallpaper.id.clone(), wallpaper.path.clone());
+        }
+    }
+
+    /// `(id, url)` pairs recorded for `collection_id`.
+    fn wallpapers(&self, collection_id: u64) -> Vec<(String, String)> {
+        let seen = self.0.lock().unwrap();
+        if collection_id == 0 || seen.0 != collection_id {
+            return Vec::new();
+        }
+        seen.1.iter().map(|(id, url)| (id.clone(), url.clone())).collect()
+    }
+}
+
+/// Files that must survive eviction: the current wallpaper, everything queued,
+/// the cycled collection (Wallhaven favorites are collections) and whatever
+/// the prefetcher was last told comes next.
+pub fn protected_files(app: &tauri::AppHandle, settings: &Settings) -> HashSet<PathBuf> {
+    let history = crate::history::get_history(app.clone());
+    let queue = crate::queue::get_queue(app.clone());
+    let upcoming = app.state::<Prefetcher>().upcoming();
+    let collection = app
+        .state::<CycleCollection>()
+        .wallpapers(settings.collection_cycle_collection_id);
+    let current = history.first().map(|e| (e.id.as_str(), e.path.as_str()));
+    cached_paths(
+        &cacheindex::snapshot(app),
+        current
+            .into_iter()
+            .chain(queue.iter().map(|w| (w.id.as_str(), w.path.as_str())))
+            .chain(upcoming.iter().map(|w| (w.id.as_str(), w.path.as_str())))
+            .chain(collection.iter().map(|(id, url)| (id.as_str(), url.as_str()))),
+    )
+}
+
+/// Picks what to evict, least recently used first: anything older than
+/// `max_age`, then whatever it takes to bring the total under `cap` bytes.
+/// Protected files are never picked; a zero limit is disabled.
+fn eviction_candidates<'a>(
+    entries: &'a [CacheEntry],
+    protected: &HashSet<PathBuf>,
+    cap: u64,
+    max_age: Duration,
+    now: SystemTime,
+) -> Vec<&'a CacheEntry> {
+    let mut oldest_first: Vec<&CacheEntry> = entries.iter().collect();
+    oldest_first.sort_by_key(|e| e.last_used);
+    let mut total: u64 = entries.iter().map(|e| e.size).sum();
+
+    let mut candidates = Vec::new();
+    for entry in oldest_first.into_iter().filter(|e| !protected.contains(&e.path)) {
+        let too_old =
+            !max_age.is_zero() && now.duration_since(entry.last_used).unwrap_or_default() > max_age;
+        let over_cap = cap > 0 && total > cap;
+        if too_old || over_cap {
+            total -= entry.size;
+            candidates.push(entry);
+        }
+    }
+    candidates
+}
+
+/// Removes cached images and thumbnails older than `cache_max_age_days`, then
+/// the least recently used ones until the cache fits in `cache_max_mb`.
+pub fn enforce_limits(app: &tauri::AppHandle) -> Result<(), AppError> {
+    let settings = load_settings(app.clone());
+    if settings.cache_max_mb == 0 && settings.cache_max_age_days == 0 {
+        return Ok(());
+    }
+
+    let protected = protected_files(app, &settings);
+    let thumbs_dir = crate::thumbs::dir(app)?;
+    let mut entries = entries(app)?;
+    entries.extend(crate::thumbs::entries(app)?);
+    let mut total: u64 = entries.iter().map(|e| e.size).sum();
+    let cap = settings.cache_max_mb.saturating_mul(1024 * 1024);
+    let max_age = Duration::from_secs(u64::from(settings.cache_max_age_days) * 24 * 60 * 60);
+
+    let mut evicted = Vec::new();
+    for entry in eviction_candidates(&entries, &protected, cap, max_age, SystemTime::now()) {
+        match fs::remove_file(&entry.path) {
+            Ok(()) => {
+                debug!("cache: evicted {:?} ({} bytes)", entry.path, entry.size);
+                total -= entry.size;
+                if !entry.path.starts_with(&thumbs_dir) {
+                    evicted.push(filename(&entry.path));
+                }
+            }
+            Err(e) => warn!("cache: couldn't evict {:?}: {e}", entry.path),
+        }
+    }
+    if !evicted.is_empty() {
+        info!("cache: evicted {} files, {} bytes remain", evicted.len(), total);
+        cacheindex::forget(app, &evicted);
+    }
+    Ok(())
+}
+
+/// Cache paths referenced by history and by the queue.
+fn referenced_files(
+    app: &tauri::AppHandle,
+    index: &Snapshot,
+) -> (HashSet<PathBuf>, HashSet<PathBuf>) {
+    let history = crate::history::get_history(app.clone());
+    let queue = crate::queue::get_queue(app.clone());
+    (
+        cached_paths(index, history.iter().map(|e| (e.id.as_str(), e.path.as_str()))),
+        cached_paths(index, queue.iter().map(|w| (w.id.as_str(), w.path.as_str()))),
+    )
+}
+
+fn filename(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// `wallhaven-abc123.jpg` -> `abc123`. Only used for files the index doesn't know.
+fn wallpaper_id(filename: &str) -> String {
+    let stem = filename.rsplit_once('.').map_or(filename, |(stem, _)| stem);
+    stem.strip_prefix("wallhaven-").unwrap_or(stem).to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct CachedImage {
+    pub id: String,
+    pub source: String,
+    pub filename: String,
+    pub size: u64,
+    /// 0 when unknown.
+    pub width: u32,
+    pub height: u32,
+    /// RFC 3339.
+    pub last_used: String,
+    pub current: bool,
+    pub in_history: bool,
+    pub in_queue: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheUsage {
+    pub files: usize,
+    /// Full-size images and thumbnails together.
+    pub bytes: u64,
+    pub thumbnail_bytes: u64,
+    /// Bytes used by images that history or the queue still refer to.
+    pub referenced_bytes: u64,
+    /// The configured cap in bytes, 0 when unlimited.
+    pub max_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurgeResult {
+    pub removed: usize,
+    pub freed_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityIssue {
+    pub filename: String,
+    pub error: String,
+    pub removed: bool,
+}
+
+fn remove_entries<'a>(
+    app: &tauri::AppHandle,
+    entries: impl Iterator<Item = &'a CacheEntry>,
+) -> PurgeResult {
+    let mut result = PurgeResult {
+        removed: 0,
+        freed_bytes: 0,
+    };
+    let mut removed = Vec::new();
+    for entry in entries {
+        match fs::remove_file(&entry.path) {
+            Ok(()) => {
+                removed.push(filename(&entry.path));
+                result.removed += 1;
+                result.freed_bytes += entry.size;
+            }
+            Err(e) => warn!("cache: couldn't remove {:?}: {e}", entry.path),
+        }
+    }
+    info!("cache: purged {} files ({} bytes)", result.removed, result.freed_bytes);
+    cacheindex::forget(app, &removed);
+    result
+}
+
+/// Cached images, most recently used first.
+#[tauri::command]
+pub fn list_cache(app: tauri::AppHandle) -> Result<Vec<CachedImage>, AppError> {
+    let mut entries = entries(&app)?;
+    entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+    let index = cacheindex::snapshot(&app);
+    let current = crate::history::get_history(app.clone())
+        .first()
+        .and_then(|e| index.lookup(&CacheKey::new(&e.id, &e.path)));
+    let (history, queue) = referenced_files(&app, &index);
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let filename = filename(&entry.path);
+            let (id, source, width, height) = match entry.index {
+                Some(index) => (index.id, index.source, index.width, index.height),
+                None => (wallpaper_id(&filename), String::new(), 0, 0),
+            };
+            CachedImage {
+                id,
+                source,
+                size: entry.size,
+                width,
+                height,
+                last_used: chrono::DateTime::<chrono::Utc>::from(entry.last_used).to_rfc3339(),
+                current: current.as_ref() == Some(&entry.path),
+                in_history: history.contains(&entry.path),
+                in_queue: queue.contains(&entry.path),
+                filename,
+            }
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn cache_usage(app: tauri::AppHandle) -> Result<CacheUsage, AppError> {
+    let entries = entries(&app)?;
+    let thumbnail_bytes = crate::thumbs::entries(&app)?.iter().map(|e| e.size).sum();
+    let (history, queue) = referenced_files(&app, &cacheindex::snapshot(&app));
+    let settings = load_settings(app);
+    Ok(CacheUsage {
+        files: entries.len(),
+        bytes: entries.iter().map(|e| e.size).sum::<u64>() + thumbnail_bytes,
+        thumbnail_bytes,
+        referenced_bytes: entries
+            .iter()
+            .filter(|e| history.contains(&e.path) || queue.contains(&e.path))
+            .map(|e| e.size)
+            .sum(),
+        max_bytes: settings.cache_max_mb.saturating_mul(1024 * 1024),
+    })
+}
+
+/// Removes the named cached images. Names that aren't in the cache are ignored.
+#[tauri::command]
+pub fn purge_cache(app: tauri::AppHandle, filenames: Vec<String>) -> Result<PurgeResult, AppError> {
+    let entries = entries(&app)?;
+    let selected = entries
+        .iter()
+        .filter(|e| filenames.iter().any(|name| *name == filename(&e.path)));
+    Ok(remove_entries(&app, selected))
+}
+
+/// Removes every cached image and thumbnail that neither history nor the
+/// queue refers to.
+#[tauri::command]
+pub fn purge_unreferenced_cache(app: tauri::AppHandle) -> Result<PurgeResult, AppError> {
+    let entries = entries(&app)?;
+    let (history, queue) = referenced_files(&app, &cacheindex::snapshot(&app));
+    let unreferenced = entries
+        .iter()
+        .filter(|e| !history.contains(&e.path) && !queue.contains(&e.path));
+    let mut result = remove_entries(&app, unreferenced);
+
+    let referenced_ids: HashSet<String> = crate::history::get_history(app.clone())
+        .into_iter()
+        .map(|e| e.id)
+        .chain(crate::queue::get_queue(app.clone()).into_iter().map(|w| w.id))
+        .collect();
+    for thumb in crate::thumbs::entries(&app)? {
+        if referenced_ids.contains(&crate::thumbs::wallpaper_id(&thumb)) {
+            continue;
+        }
+        match fs::remove_file(&thumb.path) {
+            Ok(()) => {
+                result.removed += 1;
+                result.freed_bytes += thumb.size;
+            }
+            Err(e) => warn!("cache: couldn't remove {:?}: {e}", thumb.path),
+        }
+    }
+    Ok(result)
+}
+
+/// Compares a file against the SHA-256 the index recorded when it was downloaded.
+fn checksum_matches(entry: &CacheEntry) -> Result<(), String> {
+    let Some(expected) = entry.index.as_ref().map(|e| &e.sha256).filter(|s| !s.is_empty()) else {
+        return Ok(());
+    };
+    let actual = cacheindex::sha256_file(&entry.path).map_err(|e| e.to_string())?;
+    if actual == *expected {
+        Ok(())
+    } else {
+        Err(format!("checksum mismatch (expected {expected}, got {actual})"))
+    }
+}
+
+/// Fully decodes every cached image and verifies its checksum, reporting the
+/// ones that fail and deleting them when `remove_corrupt` is set.
+#[tauri::command]
+pub async fn check_cache_integrity(
+    app: tauri::AppHandle,
+    remove_corrupt: bool,
+) -> Result<Vec<IntegrityIssue>, AppError> {
+    let entries = entries(&app)?;
+    let checked = entries.len();
+    let issues = tokio::task::spawn_blocking(move || {
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let error = image::ImageReader::open(&entry.path)
+                    .and_then(|reader| reader.with_guessed_format())
+                    .map_err(|e| e.to_string())
+                    .and_then(|reader| reader.decode().map_err(|e| e.to_string()))
+                    .and_then(|_| checksum_matches(entry))
+                    .err()?;
+                let removed = remove_corrupt && fs::remove_file(&entry.path).is_ok();
+                warn!("cache: {:?} failed the integrity check: {error}", entry.path);
+                Some(IntegrityIssue {
+                    filename: filename(&entry.path),
+                    error,
+                    removed,
+                })
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| AppError::io(format!("integrity check failed: {e}")))?;
+    info!("cache: checked {} files, {} failed", checked, issues.len());
+    let removed: Vec<String> = issues
+        .iter()
+        .filter(|issue| issue.removed)
+        .map(|issue| issue.filename.clone())
+        .collect();
+    cacheindex::forget(&app, &removed);
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: u64, days_old: u64, now: SystemTime) -> CacheEntry {
+        CacheEntry {
+            path: PathBuf::from(name),
+            size,
+            last_used: now - Duration::from_secs(days_old * 24 * 60 * 60),
+            index: None,
+        }
+    }
+
+    fn names(candidates: &[&CacheEntry]) -> Vec<String> {
+        candidates.iter().map(|e| filename(&e.path)).collect()
+    }
+
+    #[test]
+    fn eviction_skips_protected_files_when_over_cap() {
+        let now = SystemTime::now();
+        let entries = vec![
+            entry("favorite.jpg", 50, 9, now),
+            entry("old.jpg", 30, 5, now),
+            entry("recent.jpg", 30, 1, now),
+        ];
+        let protected = HashSet::from([PathBuf::from("favorite.jpg")]);
+        let picked = eviction_candidates(&entries, &protected, 60, Duration::ZERO, now);
+        assert_eq!(names(&picked), ["old.jpg", "recent.jpg"]);
+    }
+
+    #[test]
+    fn eviction_skips_protected_files_past_max_age() {
+        let now = SystemTime::now();
+        let entries = vec![entry("queued.jpg", 10, 40, now), entry("stale.jpg", 10, 31, now)];
+        let protected = HashSet::from([PathBuf::from("queued.jpg")]);
+        let max_age = Duration::from_secs(30 * 24 * 60 * 60);
+        let picked = eviction_candidates(&entries, &protected, 0, max_age, now);
+        assert_eq!(names(&picked), ["stale.jpg"]);
+    }
+
+    #[test]
+    fn eviction_stops_once_under_cap() {
+        let now = SystemTime::now();
+        let entries = vec![entry("a.jpg", 40, 3, now), entry("b.jpg", 40, 2, now)];
+        let picked = eviction_candidates(&entries, &HashSet::new(), 50, Duration::ZERO, now);
+        assert_eq!(names(&picked), ["a.jpg"]);
+    }
+}
@@ -0,0 +1,316 @@
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::download::cache_dir;
+use crate::error::AppError;
+use crate::wallhaven::Wallpaper;
+
+/// Serializes read-modify-write cycles on `index.json`; downloads and
+/// prefetches update it concurrently.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Identity of a cached full-size image: the host it came from plus its id
+/// there, so images from different sources can't collide on a filename.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub source: String,
+    pub id: String,
+}
+
+impl CacheKey {
+    pub fn new(id: &str, url: &str) -> Self {
+        Self {
+            source: source_of(url),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn for_wallpaper(wallpaper: &Wallpaper) -> Self {
+        Self::new(&wallpaper.id, &wallpaper.path)
+    }
+
+    /// `{source}-{id}.{ext}`. For Wallhaven this is the image's own filename,
+    /// so files cached before the index existed keep their names.
+    fn filename(&self, url: &str) -> String {
+        let ext = url
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .filter(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or_else(|| "jpg".to_string());
+        format!("{}-{}.{ext}", self.source, sanitize(&self.id))
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.source, self.id)
+    }
+}
+
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// `wallhaven` for any `*.wallhaven.cc` URL, otherwise the host with
+/// punctuation replaced.
+fn source_of(url: &str) -> String {
+    let host = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if host == "wallhaven.cc" || host.ends_with(".wallhaven.cc") {
+        return "wallhaven".to_string();
+    }
+    match sanitize(&host) {
+        s if s.is_empty() => "local".to_string(),
+        s => s,
+    }
+}
+
+/// What the index knows about one cached image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub source: String,
+    pub id: String,
+    /// Original download URL, before any image host override.
+    pub url: String,
+    pub filename: String,
+    pub size: u64,
+    /// Hex SHA-256 of the file.
+    pub sha256: String,
+    pub width: u32,
+    pub height: u32,
+    /// RFC 3339.
+    pub last_access: String,
+}
+
+impl IndexEntry {
+    pub fn key(&self) -> CacheKey {
+        CacheKey {
+            source: self.source.clone(),
+            id: self.id.clone(),
+        }
+    }
+}
+
+type Index = HashMap<String, IndexEntry>;
+
+fn index_path(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    Ok(cache_dir(app)?.join("index.json"))
+}
+
+fn load(app: &tauri::AppHandle) -> Index {
+    index_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &tauri::AppHandle, index: &Index) -> Result<(), AppError> {
+    let path = index_path(app)?;
+    let tmp = path.with_extension("json.part");
+    fs::write(&tmp, serde_json::to_string_pretty(index)?)?;
+    Ok(fs::rename(&tmp, &path)?)
+}
+
+fn update<R>(app: &tauri::AppHandle, f: impl FnOnce(&mut Index) -> R) -> Result<R, AppError> {
+    let _lock = INDEX_LOCK.lock().unwrap();
+    let mut index = load(app);
+    let result = f(&mut index);
+    save(app, &index)?;
+    Ok(result)
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Every indexed image.
+pub fn entries(app: &tauri::AppHandle) -> Vec<IndexEntry> {
+    load(app).into_values().collect()
+}
+
+/// The index loaded once, for checking many keys without rereading index.json.
+pub struct Snapshot {
+    dir: Option<PathBuf>,
+    index: Index,
+}
+
+impl Snapshot {
+    /// The cached file for `key`, if the index has one and it still exists.
+    pub fn lookup(&self, key: &CacheKey) -> Option<PathBuf> {
+        let entry = self.index.get(&key.to_string())?;
+        let path = self.dir.as_ref()?.join(&entry.filename);
+        path.exists().then_some(path)
+    }
+}
+
+pub fn snapshot(app: &tauri::AppHandle) -> Snapshot {
+    Snapshot {
+        dir: cache_dir(app).ok(),
+        index: load(app),
+    }
+}
+
+/// The cached file for `key`, if the index has one and it still exists.
+pub fn lookup(app: &tauri::AppHandle, key: &CacheKey) -> Option<PathBuf> {
+    snapshot(app).lookup(key)
+}
+
+/// Like [`lookup`], along with what the index recorded about the file.
+pub fn lookup_entry(app: &tauri::AppHandle, key: &CacheKey) -> Option<(PathBuf, IndexEntry)> {
+    let snapshot = snapshot(app);
+    let path = snapshot.lookup(key)?;
+    let entry = snapshot.index.get(&key.to_string())?.clone();
+    Some((path, entry))
+}
+
+/// Where a new download for `key` should be written.
+pub fn path_for(app: &tauri::AppHandle, key: &CacheKey, url: &str) -> Result<PathBuf, AppError> {
+    Ok(cache_dir(app)?.join(key.filename(url)))
+}
+
+/// Adds or replaces the entry for a file that was just written.
+pub fn record(
+    app: &tauri::AppHandle,
+    key: &CacheKey,
+    url: &str,
+    path: &Path,
+    sha256: String,
+) -> Result<(), AppError> {
+    let size = fs::metadata(path)?.len();
+    let (width, height) = image::image_dimensions(path).unwrap_or_else(|e| {
+        warn!("cacheindex: couldn't read dimensions of {:?}: {e}", path);
+        (0, 0)
+    });
+    let entry = IndexEntry {
+        source: key.source.clone(),
+        id: key.id.clone(),
+        url: url.to_string(),
+        filename: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        size,
+        sha256,
+        width,
+        height,
+        last_access: chrono::Utc::now().to_rfc3339(),
+    };
+    update(app, |index| index.insert(key.to_string(), entry))?;
+    Ok(())
+}
+
+/// Marks an entry as just used; its `last_access` is the LRU clock.
+pub fn touch(app: &tauri::AppHandle, key: &CacheKey) {
+    let result = update(app, |index| {
+        if let Some(entry) = index.get_mut(&key.to_string()) {
+            entry.last_access = chrono::Utc::now().to_rfc3339();
+        }
+    });
+    if let Err(e) = result {
+        debug!("cacheindex: couldn't touch {}: {e}", key);
+    }
+}
+
+/// Drops the entries for files that were deleted, along with their wallpapers'
+/// cached thumbnails.
+pub fn forget(app: &tauri::AppHandle, filenames: &[String]) {
+    if filenames.is_empty() {
+        return;
+    }
+    let result = update(app, |index| {
+        let mut ids = Vec::new();
+        index.retain(|_, e| {
+            let keep = !filenames.contains(&e.filename);
+            if !keep {
+                ids.push(e.id.clone());
+            }
+            keep
+        });
+        ids
+    });
+    match result {
+        Ok(ids) => crate::thumbs::remove(app, &ids),
+        Err(e) => warn!("cacheindex: couldn't update index: {e}"),
+    }
+}
+
+/// Brings the index in line with the cache dir: entries whose file is gone
+/// are dropped and image files without an entry (cached before the index
+/// existed) are adopted.
+pub fn reconcile(app: &tauri::AppHandle) -> Result<(), AppError> {
+    let dir = cache_dir(app)?;
+    let files: Vec<String> = crate::cache::entries(app)?
+        .iter()
+        .map(|e| e.path.file_name().unwrap_or_default().to_string_lossy().into_owned())
+        .collect();
+
+    let adopted = update(app, |index| {
+        index.retain(|_, e| files.contains(&e.filename));
+        let mut adopted = 0;
+        for filename in &files {
+            if index.values().any(|e| e.filename == *filename) {
+                continue;
+            }
+            // Legacy names are `wallhaven-{id}.{ext}`
+            let stem = filename.rsplit_once('.').map_or(filename.as_str(), |(stem, _)| stem);
+            let Some((source, id)) = stem.rsplit_once('-').filter(|(_, id)| id.len() >= 2) else {
+                continue;
+            };
+            let path = dir.join(filename);
+            let url = match source {
+                "wallhaven" => format!("https://w.wallhaven.cc/full/{}/{filename}", &id[..2]),
+                _ => String::new(),
+            };
+            let (width, height) = image::image_dimensions(&path).unwrap_or((0, 0));
+            let entry = IndexEntry {
+                source: source.to_string(),
+                id: id.to_string(),
+                url,
+                filename: filename.clone(),
+                size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                sha256: sha256_file(&path).unwrap_or_default(),
+                width,
+                height,
+                last_access: fs::metadata(&path)
+                    .and_then(|m| m.modified())
+                    .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+                    .unwrap_or_else(|_| chrono::Utc::now().to_rfc3339()),
+            };
+            index.insert(entry.key().to_string(), entry);
+            adopted += 1;
+        }
+        adopted
+    })?;
+    if adopted > 0 {
+        info!("cacheindex: adopted {} unindexed files", adopted);
+    }
+    Ok(())
+}
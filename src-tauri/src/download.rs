@@ -0,0 +1,405 @@
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+
+use crate::bandwidth::Meter;
+use crate::cacheindex::{self, CacheKey, IndexEntry};
+use crate::error::{AppError, ErrorKind};
+use crate::http::WallhavenClient;
+use crate::settings::load_settings;
+use crate::wallhaven::{api_key_for, image_url, Wallpaper};
+
+/// Progress events are rate-limited to this interval per download.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Replaces the client's short API timeout for full-size images.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+pub fn cache_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| AppError::io(format!("cache dir error: {e}")))?;
+    fs::create_dir_all(&dir).ok();
+    Ok(dir)
+}
+
+/// Identifies an image by its magic bytes, returning its MIME type.
+pub fn image_kind(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Checks that a cached file starts like an image and, when known, has the
+/// size the API reported.
+pub fn verify_file(path: &Path, expected_size: Option<u64>) -> Result<(), AppError> {
+    let mut file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    if let Some(expected) = expected_size.filter(|&s| s > 0) {
+        if len != expected {
+            return Err(AppError::invalid_image(format!(
+                "{} is {len} bytes, expected {expected}",
+                path.display()
+            )));
+        }
+    }
+    let mut header = [0u8; 12];
+    let read = file.read(&mut header)?;
+    if image_kind(&header[..read]).is_none() {
+        return Err(AppError::invalid_image(format!("{} is not an image", path.display())));
+    }
+    Ok(())
+}
+
+/// [`verify_file`], plus the SHA-256 the index recorded when the file was
+/// written, so a cached file that was modified or damaged isn't reused.
+fn verify_cached(path: &Path, entry: &IndexEntry, expected_size: Option<u64>) -> Result<(), AppError> {
+    verify_file(path, expected_size)?;
+    if entry.sha256.is_empty() {
+        return Ok(());
+    }
+    let actual = cacheindex::sha256_file(path)?;
+    if actual != entry.sha256 {
+        return Err(AppError::invalid_image(format!(
+            "{} doesn't match its recorded checksum",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// `wallhaven-abc123.jpg` -> `wallhaven-abc123.jpg.part`.
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Payload of the `download-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub wallpaper_id: String,
+    /// `"started"`, `"progress"`, `"done"`, `"failed"` or `"cancelled"`.
+    pub state: &'static str,
+    pub received: u64,
+    /// From `Content-Length`, falling back to the API's `file_size`.
+    pub total: Option<u64>,
+}
+
+type Outcome = Option<Result<PathBuf, AppError>>;
+
+struct Transfer {
+    done: watch::Receiver<Outcome>,
+    cancel: watch::Sender<bool>,
+}
+
+/// Streams full-size images into the cache. Concurrent requests for the same
+/// image (by [`CacheKey`]) share one transfer; any of them can be cancelled by
+/// wallpaper id. Managed as Tauri state.
+#[derive(Default)]
+pub struct DownloadManager {
+    transfers: Mutex<HashMap<CacheKey, Transfer>>,
+}
+
+/// Drops the transfer's map entry however the download future ends, so
+/// waiters see the sender go away rather than hang.
+struct TransferGuard<'a> {
+    manager: &'a DownloadManager,
+    key: &'a CacheKey,
+}
+
+impl Drop for TransferGuard<'_> {
+    fn drop(&mut self) {
+        self.manager.transfers.lock().unwrap().remove(self.key);
+    }
+}
+
+impl DownloadManager {
+    /// Returns the cached full-size image for `wallpaper`, downloading it when
+    /// missing or when the cached copy fails verification.
+    pub async fn fetch(
+        &self,
+        app: &tauri::AppHandle,
+        http: &WallhavenClient,
+        wallpaper: &Wallpaper,
+    ) -> Result<PathBuf, AppError> {
+        self.transfer(app, http, wallpaper, false).await
+    }
+
+    /// Like [`DownloadManager::fetch`] but without progress events, for
+    /// background work the user didn't ask for.
+    pub async fn prefetch(
+        &self,
+        app: &tauri::AppHandle,
+        http: &WallhavenClient,
+        wallpaper: &Wallpaper,
+    ) -> Result<PathBuf, AppError> {
+        self.transfer(app, http, wallpaper, true).await
+    }
+
+    async fn transfer(
+        &self,
+        app: &tauri::AppHandle,
+        http: &WallhavenClient,
+        wallpaper: &Wallpaper,
+        muted: bool,
+    ) -> Result<PathBuf, AppError> {
+        let key = CacheKey::for_wallpaper(wallpaper);
+        let started = {
+            let mut transfers = self.transfers.lock().unwrap();
+            match transfers.get(&key) {
+                Some(transfer) => Err(transfer.done.clone()),
+                None => {
+                    let (done_tx, done_rx) = watch::channel(None);
+                    let (cancel_tx, cancel_rx) = watch::channel(false);
+                    let transfer = Transfer {
+                        done: done_rx,
+                        cancel: cancel_tx,
+                    };
+                    transfers.insert(key.clone(), transfer);
+                    Ok((done_tx, cancel_rx))
+                }
+            }
+        };
+        let (done_tx, cancel_rx) = match started {
+            Ok(channels) => channels,
+            Err(done) => {
+                debug!("download: joining in-flight transfer for {}", key);
+                return wait(done).await;
+            }
+        };
+        let _guard = TransferGuard {
+            manager: self,
+            key: &key,
+        };
+
+        let progress = Progress {
+            app,
+            id: &wallpaper.id,
+            muted,
+        };
+        let outcome = fetch_to_cache(app, http, wallpaper, &key, &progress, cancel_rx).await;
+        let state = match &outcome {
+            Ok(_) => "done",
+            Err(e) if e.kind == ErrorKind::Cancelled => "cancelled",
+            Err(_) => "failed",
+        };
+        progress.emit(state, 0, None);
+        done_tx.send_replace(Some(outcome.clone()));
+        outcome
+    }
+
+    /// Cancels the transfers for `wallpaper_id`. Returns false when none is running.
+    pub fn cancel(&self, wallpaper_id: &str) -> bool {
+        let transfers = self.transfers.lock().unwrap();
+        let mut cancelled = false;
+        for (key, transfer) in transfers.iter().filter(|(key, _)| key.id == wallpaper_id) {
+            info!("download: cancelling {}", key);
+            transfer.cancel.send_replace(true);
+            cancelled = true;
+        }
+        cancelled
+    }
+}
+
+async fn wait(mut done: watch::Receiver<Outcome>) -> Result<PathBuf, AppError> {
+    loop {
+        let outcome = done.borrow_and_update().clone();
+        if let Some(outcome) = outcome {
+            return outcome;
+        }
+        if done.changed().await.is_err() {
+            let outcome = done.borrow().clone();
+            return outcome.unwrap_or_else(|| Err(AppError::cancelled("download was abandoned")));
+        }
+    }
+}
+
+/// Emits `download-progress` for one wallpaper, unless muted.
+struct Progress<'a> {
+    app: &'a tauri::AppHandle,
+    id: &'a str,
+    muted: bool,
+}
+
+impl Progress<'_> {
+    fn emit(&self, state: &'static str, received: u64, total: Option<u64>) {
+        if self.muted {
+            return;
+        }
+        let event = DownloadProgress {
+            wallpaper_id: self.id.to_string(),
+            state,
+            received,
+            total,
+        };
+        let _ = self.app.emit("download-progress", event);
+    }
+}
+
+async fn fetch_to_cache(
+    app: &tauri::AppHandle,
+    http: &WallhavenClient,
+    wallpaper: &Wallpaper,
+    key: &CacheKey,
+    progress: &Progress<'_>,
+    cancel: watch::Receiver<bool>,
+) -> Result<PathBuf, AppError> {
+    if let Some((cached, entry)) = cacheindex::lookup_entry(app, key) {
+        match verify_cached(&cached, &entry, Some(wallpaper.file_size)) {
+            Ok(()) => {
+                debug!("fetch_to_cache: using cached file {:?}", cached);
+                cacheindex::touch(app, key);
+                return Ok(cached);
+            }
+            Err(e) => {
+                warn!("fetch_to_cache: discarding corrupt cache entry: {e}");
+                fs::remove_file(&cached).ok();
+            }
+        }
+    }
+    let file_path = cacheindex::path_for(app, key, &wallpaper.path)?;
+
+    let settings = load_settings(app.clone());
+    crate::bandwidth::check(app, &settings, wallpaper.file_size)?;
+
+    info!("fetch_to_cache: downloading to {:?}", file_path);
+    let url = image_url(&settings, &wallpaper.path);
+    let mut req = http.http().get(&url).timeout(DOWNLOAD_TIMEOUT);
+    if let Some(key) = api_key_for(&settings, &url) {
+        req = req.header("X-API-Key", key);
+    }
+
+    let response = req.send().await.map_err(AppError::from);
+    http.observe(&response);
+    let response = response.map_err(|e| {
+        error!("fetch_to_cache: download failed: {e}");
+        e.context("download failed")
+    })?;
+
+    let status = response.status();
+    debug!("fetch_to_cache: download status={}", status);
+    if !status.is_success() {
+        error!("fetch_to_cache: image host returned status={}", status);
+        return Err(AppError::from_status(status).context("download failed"));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if !content_type.is_empty() && !content_type.starts_with("image/") {
+        error!("fetch_to_cache: unexpected content-type {}", content_type);
+        return Err(AppError::invalid_image(format!("expected an image, got {content_type}")));
+    }
+
+    let expected = Some(wallpaper.file_size).filter(|&s| s > 0);
+    if let (Some(length), Some(expected)) = (response.content_length(), expected) {
+        if length != expected {
+            return Err(AppError::invalid_image(format!(
+                "download is {length} bytes, expected {expected}"
+            )));
+        }
+    }
+
+    // Written to a .part file and renamed into place, so a crash never leaves
+    // a truncated file under the final name
+    let tmp = part_path(&file_path);
+    let result = stream_to_file(progress, response, &tmp, expected, cancel).await;
+    let result = result.and_then(|sha256| {
+        fs::rename(&tmp, &file_path)?;
+        Ok(sha256)
+    });
+    let sha256 = match result {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            fs::remove_file(&tmp).ok();
+            return Err(e);
+        }
+    };
+    cacheindex::record(app, key, &wallpaper.path, &file_path, sha256)?;
+    Ok(file_path)
+}
+
+async fn stream_to_file(
+    progress: &Progress<'_>,
+    mut response: reqwest::Response,
+    tmp: &Path,
+    expected: Option<u64>,
+    mut cancel: watch::Receiver<bool>,
+) -> Result<String, AppError> {
+    let total = response.content_length().or(expected);
+    let mut file = tokio::fs::File::create(tmp)
+        .await
+        .map_err(|e| AppError::from(e).context("write failed"))?;
+    let mut header = Vec::with_capacity(12);
+    let mut hasher = Sha256::new();
+    let mut meter = Meter::new(progress.app);
+    let mut received = 0u64;
+    let mut last_emit = Instant::now();
+    progress.emit("started", 0, total);
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = response.chunk() => chunk.map_err(|e| {
+                error!("fetch_to_cache: reading image failed: {e}");
+                AppError::from(e).context("reading image failed")
+            })?,
+            Ok(_) = cancel.wait_for(|&c| c) => {
+                return Err(AppError::cancelled("download cancelled"));
+            }
+        };
+        let Some(chunk) = chunk else { break };
+
+        if header.len() < 12 {
+            let take = (12 - header.len()).min(chunk.len());
+            header.extend_from_slice(&chunk[..take]);
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| AppError::from(e).context("write failed"))?;
+        received += chunk.len() as u64;
+        meter.add(chunk.len() as u64);
+
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            progress.emit("progress", received, total);
+            last_emit = Instant::now();
+        }
+    }
+    info!("fetch_to_cache: downloaded {} bytes", received);
+
+    if let Some(expected) = expected.filter(|&e| e != received) {
+        return Err(AppError::invalid_image(format!(
+            "download is {received} bytes, expected {expected}"
+        )));
+    }
+    if image_kind(&header).is_none() {
+        return Err(AppError::invalid_image("downloaded file is not an image"));
+    }
+    file.sync_all()
+        .await
+        .map_err(|e| AppError::from(e).context("write failed"))?;
+    Ok(cacheindex::to_hex(&hasher.finalize()))
+}
+
+#[tauri::command]
+pub fn cancel_download(downloads: tauri::State<'_, DownloadManager>, wallpaper_id: String) -> bool {
+    downloads.cancel(&wallpaper_id)
+}
@@ -0,0 +1,154 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Machine-readable category so the UI can react without matching on text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Network,
+    Unauthorized,
+    RateLimited,
+    NotFound,
+    Parse,
+    Io,
+    InvalidInput,
+    UnsupportedDesktop,
+    SetterFailed,
+    /// A download or cached file that isn't the image it claims to be.
+    InvalidImage,
+    Cancelled,
+    /// Metered mode refused a download the bandwidth budget can't cover.
+    BudgetExceeded,
+    /// The wallpaper's uploader is on the account's user blacklist.
+    Blacklisted,
+}
+
+/// Error returned by every Tauri command. Serialized as
+/// `{ kind, status, retry_after, message }`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    /// HTTP status of the failing response, when there was one.
+    pub status: Option<u16>,
+    /// Seconds to wait before retrying, taken from `Retry-After` on 429s.
+    pub retry_after: Option<u64>,
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status: None,
+            retry_after: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Network, message)
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Parse, message)
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Io, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    pub fn unsupported_desktop(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::UnsupportedDesktop, message)
+    }
+
+    pub fn setter_failed(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::SetterFailed, message)
+    }
+
+    pub fn invalid_image(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidImage, message)
+    }
+
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Cancelled, message)
+    }
+
+    pub fn budget_exceeded(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::BudgetExceeded, message)
+    }
+
+    pub fn blacklisted(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Blacklisted, message)
+    }
+
+    /// Maps a non-success HTTP status onto the matching kind.
+    pub fn from_status(status: reqwest::StatusCode) -> Self {
+        let kind = match status.as_u16() {
+            401 | 403 => ErrorKind::Unauthorized,
+            404 => ErrorKind::NotFound,
+            429 => ErrorKind::RateLimited,
+            _ => ErrorKind::Network,
+        };
+        Self {
+            kind,
+            status: Some(status.as_u16()),
+            retry_after: None,
+            message: format!("API error: status {status}"),
+        }
+    }
+
+    pub fn with_retry_after(mut self, seconds: Option<u64>) -> Self {
+        self.retry_after = seconds;
+        self
+    }
+
+    /// True when the server couldn't be reached or failed (connection errors,
+    /// timeouts, 5xx), as opposed to rejecting the request.
+    pub fn is_offline(&self) -> bool {
+        self.kind == ErrorKind::Network && self.status.is_none_or(|s| s >= 500)
+    }
+
+    /// Prefixes the message with context, keeping kind and status.
+    pub fn context(mut self, context: &str) -> Self {
+        self.message = format!("{context}: {}", self.message);
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => Self::from_status(status),
+            None if e.is_decode() => Self::parse(format!("reading body failed: {e}")),
+            None => Self::network(format!("request failed: {e}")),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        Self::io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::parse(format!("parse failed: {e}"))
+    }
+}
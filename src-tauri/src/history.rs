@@ -1,8 +1,12 @@
+use log::warn;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs;
 use tauri::Manager;
 
+use crate::download::DownloadManager;
+use crate::error::AppError;
+use crate::http::WallhavenClient;
 use crate::wallhaven::{Thumbs, Wallpaper};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -32,13 +36,13 @@ fn load_history_entries(app: &tauri::AppHandle) -> Vec<HistoryEntry> {
         .unwrap_or_default()
 }
 
-fn save_history_entries(app: &tauri::AppHandle, entries: &[HistoryEntry]) -> Result<(), String> {
+fn save_history_entries(app: &tauri::AppHandle, entries: &[HistoryEntry]) -> Result<(), AppError> {
     let path = history_path(app);
-    let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
-    fs::write(&path, json).map_err(|e| e.to_string())
+    let json = serde_json::to_string_pretty(entries)?;
+    Ok(fs::write(&path, json)?)
 }
 
-pub fn add_to_history(app: &tauri::AppHandle, wallpaper: &Wallpaper) -> Result<(), String> {
+pub fn add_to_history(app: &tauri::AppHandle, wallpaper: &Wallpaper) -> Result<(), AppError> {
     let mut entries = load_history_entries(app);
     entries.retain(|e| e.id != wallpaper.id);
     let entry = HistoryEntry {
@@ -60,24 +64,42 @@ pub fn get_history(app: tauri::AppHandle) -> Vec<HistoryEntry> {
     entries.into_iter().filter(|e| seen.insert(e.id.clone())).collect()
 }
 
+impl HistoryEntry {
+    /// Enough of a wallpaper record to download it again.
+    pub fn to_wallpaper(&self) -> Wallpaper {
+        Wallpaper {
+            id: self.id.clone(),
+            url: self.url.clone(),
+            path: self.path.clone(),
+            thumbs: self.thumbs.clone(),
+            resolution: self.resolution.clone(),
+            ..Wallpaper::default()
+        }
+    }
+}
+
+/// Re-applies the previous wallpaper, downloading it again if it was evicted
+/// from the cache.
 #[tauri::command]
-pub fn undo_wallpaper(app: tauri::AppHandle) -> Result<(), String> {
+pub async fn undo_wallpaper(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    downloads: tauri::State<'_, DownloadManager>,
+) -> Result<(), AppError> {
     let entries = load_history_entries(&app);
     if entries.len() < 2 {
-        return Err("No previous wallpaper to revert to".into());
+        return Err(AppError::not_found("No previous wallpaper to revert to"));
     }
     let prev = &entries[1];
-    let filename = prev.path.rsplit('/').next().unwrap_or("wallpaper.jpg");
-    let cache_dir = app
-        .path()
-        .app_cache_dir()
-        .map_err(|e| format!("cache dir error: {e}"))?;
-    let file_path = cache_dir.join(filename);
-    if !file_path.exists() {
-        return Err("Previous wallpaper not in cache".into());
-    }
+    let file_path = downloads
+        .fetch(&app, &http, &prev.to_wallpaper())
+        .await
+        .map_err(|e| {
+            warn!("undo_wallpaper: previous wallpaper unavailable: {e}");
+            e.context("Previous wallpaper is no longer cached and couldn't be downloaded")
+        })?;
     let settings = crate::settings::load_settings(app.clone());
-    crate::setwallpaper::set(file_path.to_str().unwrap(), &settings.linux_wallpaper_cmd)?;
+    crate::setwallpaper::set(file_path, settings).await?;
     // Move the previous entry to the top of history
     let mut entries = entries;
     let entry = entries.remove(1);
@@ -89,13 +111,13 @@ pub fn undo_wallpaper(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn delete_history_entry(app: tauri::AppHandle, wallpaper_id: String) -> Result<(), String> {
+pub fn delete_history_entry(app: tauri::AppHandle, wallpaper_id: String) -> Result<(), AppError> {
     let mut entries = load_history_entries(&app);
     entries.retain(|e| e.id != wallpaper_id);
     save_history_entries(&app, &entries)
 }
 
 #[tauri::command]
-pub fn clear_history(app: tauri::AppHandle) -> Result<(), String> {
+pub fn clear_history(app: tauri::AppHandle) -> Result<(), AppError> {
     save_history_entries(&app, &[])
 }
@@ -0,0 +1,254 @@
+use log::{debug, warn};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::Emitter;
+use tokio::sync::Mutex;
+
+use crate::error::AppError;
+
+/// Wallhaven allows 45 API calls per minute per client.
+const BUCKET_CAPACITY: f64 = 45.0;
+const REFILL_PER_SEC: f64 = 45.0 / 60.0;
+
+/// 429s are retried this many times before the error is handed back.
+const MAX_RETRIES: u32 = 4;
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Without these a half-dead network hangs requests instead of failing them,
+/// and offline mode never starts. Full-size downloads override the total
+/// timeout; a stalled read still fails them after `READ_TIMEOUT`.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Payload of the `wallhaven-throttled` event, emitted whenever a call is held back.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThrottleEvent {
+    /// `"rate_limit"` when our own limiter delayed the call, `"too_many_requests"` on a 429.
+    pub reason: &'static str,
+    pub label: &'static str,
+    pub delay_ms: u64,
+    pub attempt: u32,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new() -> Self {
+        Self {
+            tokens: BUCKET_CAPACITY,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * REFILL_PER_SEC).min(BUCKET_CAPACITY);
+        self.last_refill = now;
+    }
+
+    /// Takes a token, or returns how long to wait until one is available.
+    fn try_take(&mut self) -> Result<(), Duration> {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / REFILL_PER_SEC))
+        }
+    }
+}
+
+/// Payload of the `connectivity-changed` event, emitted when offline mode starts or ends.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityEvent {
+    pub offline: bool,
+}
+
+/// Pooled HTTP client shared through Tauri managed state. API calls go through
+/// [`WallhavenClient::send`] so they share one rate limit; image downloads use
+/// [`WallhavenClient::http`] directly. Also tracks whether Wallhaven is reachable.
+pub struct WallhavenClient {
+    app: tauri::AppHandle,
+    client: reqwest::Client,
+    bucket: Mutex<TokenBucket>,
+    offline: AtomicBool,
+}
+
+impl WallhavenClient {
+    pub fn new(app: tauri::AppHandle) -> Result<Self, AppError> {
+        let client = reqwest::Client::builder()
+            .user_agent("wallchemybar/0.1.0")
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(READ_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::network(format!("client error: {e}")))?;
+        Ok(Self {
+            app,
+            client,
+            bucket: Mutex::new(TokenBucket::new()),
+            offline: AtomicBool::new(false),
+        })
+    }
+
+    pub fn http(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::SeqCst)
+    }
+
+    /// Switches offline mode on or off, telling the windows when it changes.
+    /// Going offline starts a probe that switches back once Wallhaven answers.
+    pub fn set_offline(&self, offline: bool) {
+        if self.offline.swap(offline, Ordering::SeqCst) == offline {
+            return;
+        }
+        warn!("connectivity: now {}", if offline { "offline" } else { "online" });
+        let _ = self.app.emit("connectivity-changed", ConnectivityEvent { offline });
+        if offline {
+            let app = self.app.clone();
+            tauri::async_runtime::spawn(crate::offline::probe_until_online(app));
+        }
+    }
+
+    /// Updates offline mode from the outcome of any request to Wallhaven. A
+    /// 5xx counts as offline, the same as in [`AppError::is_offline`].
+    pub fn observe(&self, result: &Result<reqwest::Response, AppError>) {
+        match result {
+            Ok(response) if response.status().is_server_error() => self.set_offline(true),
+            Ok(_) => self.set_offline(false),
+            Err(e) if e.is_offline() => self.set_offline(true),
+            Err(_) => {}
+        }
+    }
+
+    /// Sends a Wallhaven API request through the limiter, retrying 429s with
+    /// `Retry-After` or jittered exponential back-off.
+    pub async fn send(
+        &self,
+        label: &'static str,
+        req: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, AppError> {
+        let request = req
+            .build()
+            .map_err(|e| AppError::invalid_input(format!("invalid request: {e}")))?;
+
+        let mut attempt = 0;
+        loop {
+            self.acquire(label, attempt).await;
+
+            let this_try = request
+                .try_clone()
+                .ok_or_else(|| AppError::invalid_input("request body cannot be retried"))?;
+            let response = self.client.execute(this_try).await.map_err(AppError::from);
+            self.observe(&response);
+            let response = response?;
+
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+
+            let retry_after = parse_retry_after(response.headers());
+            if attempt >= MAX_RETRIES {
+                warn!("{label}: still rate limited after {attempt} retries, giving up");
+                return Err(AppError::from_status(response.status())
+                    .with_retry_after(retry_after.map(|d| d.as_secs().max(1))));
+            }
+
+            // Drain the bucket so concurrent callers back off too
+            self.bucket.lock().await.tokens = 0.0;
+
+            let delay = retry_after.unwrap_or_default().max(backoff(attempt));
+            warn!("{label}: got 429, retrying in {}ms (attempt {})", delay.as_millis(), attempt + 1);
+            self.notify("too_many_requests", label, delay, attempt + 1);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn acquire(&self, label: &'static str, attempt: u32) {
+        loop {
+            let wait = match self.bucket.lock().await.try_take() {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            debug!("{label}: rate limiter delaying request by {}ms", wait.as_millis());
+            self.notify("rate_limit", label, wait, attempt);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn notify(&self, reason: &'static str, label: &'static str, delay: Duration, attempt: u32) {
+        let event = ThrottleEvent {
+            reason,
+            label,
+            delay_ms: delay.as_millis() as u64,
+            attempt,
+        };
+        let _ = self.app.emit("wallhaven-throttled", event);
+    }
+}
+
+/// `Retry-After` is either delta-seconds or an HTTP date.
+fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (at.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_seconds();
+    Some(Duration::from_secs(secs.max(0) as u64))
+}
+
+/// Exponential back-off with up to 50% random jitter so retries don't line up.
+fn backoff(attempt: u32) -> Duration {
+    let exp = BACKOFF_BASE.saturating_mul(1 << attempt.min(6)).min(BACKOFF_MAX);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let jitter = exp.mul_f64((nanos % 1000) as f64 / 2000.0);
+    exp + jitter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+
+    fn retry_after(value: &str) -> Option<Duration> {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        parse_retry_after(&headers)
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(" 5 "), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn retry_after_http_date() {
+        // A date in the past means "now"
+        assert_eq!(retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        let at = chrono::Utc::now() + chrono::Duration::seconds(90);
+        let secs = retry_after(&at.to_rfc2822()).unwrap().as_secs();
+        assert!((88..=90).contains(&secs), "{secs}");
+    }
+
+    #[test]
+    fn retry_after_missing_or_invalid() {
+        assert_eq!(parse_retry_after(&HeaderMap::new()), None);
+        assert_eq!(retry_after("soon"), None);
+    }
+}
@@ -1,7 +1,22 @@
+mod account;
+mod bandwidth;
+mod cache;
+mod cacheindex;
+mod download;
+mod error;
 mod history;
+mod http;
+mod offline;
+mod prefetch;
+mod query;
 mod queue;
+mod search;
+mod searchcache;
 mod settings;
 mod setwallpaper;
+mod tags;
+mod thumbs;
+mod users;
 mod wallhaven;
 
 #[cfg(target_os = "macos")]
@@ -17,6 +32,7 @@ use tauri::{
     tray::TrayIconBuilder,
     Manager, PhysicalPosition, WindowEvent,
 };
+use error::AppError;
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
 
 #[cfg(not(target_os = "windows"))]
@@ -28,8 +44,13 @@ fn modifier_from_str(s: &str) -> Modifiers {
     }
 }
 
+#[cfg(not(target_os = "windows"))]
+fn shortcut_error(e: tauri_plugin_global_shortcut::Error) -> AppError {
+    AppError::invalid_input(format!("failed to register shortcut: {e}"))
+}
+
 #[tauri::command]
-fn reregister_shortcuts(app: tauri::AppHandle, modifier: String) -> Result<(), String> {
+fn reregister_shortcuts(app: tauri::AppHandle, modifier: String) -> Result<(), AppError> {
     #[cfg(not(target_os = "windows"))]
     {
         let mod_key = modifier_from_str(&modifier);
@@ -51,7 +72,7 @@ fn reregister_shortcuts(app: tauri::AppHandle, modifier: String) -> Result<(), S
                     }
                 }
             }
-        }).map_err(|e| e.to_string())?;
+        }).map_err(shortcut_error)?;
         let expand = Shortcut::new(Some(mod_key | Modifiers::SHIFT), Code::KeyE);
         shortcuts.on_shortcut(expand, |app, _shortcut, event| {
             if event.state() == ShortcutState::Pressed {
@@ -67,7 +88,7 @@ fn reregister_shortcuts(app: tauri::AppHandle, modifier: String) -> Result<(), S
                     }
                 }
             }
-        }).map_err(|e| e.to_string())?;
+        }).map_err(shortcut_error)?;
     }
     #[cfg(target_os = "windows")]
     let _ = (app, modifier);
@@ -166,6 +187,9 @@ pub fn run() {
             log::info!("second instance argv: {:?}", argv);
             handle_cli_args(app, &argv);
         }))
+        .register_asynchronous_uri_scheme_protocol(thumbs::SCHEME, |ctx, request, responder| {
+            thumbs::handle(ctx.app_handle().clone(), request, responder)
+        })
         .invoke_handler(tauri::generate_handler![
             settings::load_settings,
             settings::save_settings,
@@ -173,12 +197,39 @@ pub fn run() {
             wallhaven::fetch_collections,
             wallhaven::fetch_collection_wallpapers,
             wallhaven::set_wallpaper,
+            download::cancel_download,
+            cache::list_cache,
+            cache::cache_usage,
+            cache::purge_cache,
+            cache::purge_unreferenced_cache,
+            cache::check_cache_integrity,
+            prefetch::prefetch_wallpapers,
+            offline::get_local_wallpapers,
+            offline::is_offline,
+            bandwidth::bandwidth_usage,
+            bandwidth::reset_bandwidth_usage,
+            setwallpaper::list_wallpaper_backends,
+            setwallpaper::get_desktop_wallpaper,
             wallhaven::fetch_wallpaper_tags,
+            wallhaven::fetch_wallpaper_details,
             wallhaven::validate_api_key,
+            account::preview_account_settings,
+            account::apply_account_settings,
+            tags::fetch_tag,
+            tags::search_by_tag,
+            tags::tag_frequencies,
+            users::fetch_user_collections,
+            users::fetch_user_collection_wallpapers,
+            users::fetch_user_uploads,
+            users::get_followed_users,
+            users::follow_user,
+            users::unfollow_user,
             history::get_history,
             history::clear_history,
             history::delete_history_entry,
             history::undo_wallpaper,
+            query::parse_query,
+            query::format_query,
             queue::get_queue,
             queue::add_to_queue,
             queue::remove_from_queue,
@@ -192,6 +243,22 @@ pub fn run() {
             reregister_shortcuts
         ])
         .setup(|app| {
+            app.manage(http::WallhavenClient::new(app.handle().clone())?);
+            app.manage(tags::TagCache::default());
+            app.manage(download::DownloadManager::default());
+            app.manage(prefetch::Prefetcher::default());
+            app.manage(cache::CycleCollection::default());
+
+            if let Err(e) = cacheindex::reconcile(app.handle()) {
+                log::warn!("startup cache index reconcile failed: {e}");
+            }
+            if let Err(e) = cache::enforce_limits(app.handle()) {
+                log::warn!("startup cache cleanup failed: {e}");
+            }
+            if let Err(e) = searchcache::prune(app.handle()) {
+                log::warn!("startup result cache cleanup failed: {e}");
+            }
+
             // Hide the app from the macOS Dock — it lives only in the menu bar
             #[cfg(target_os = "macos")]
             unsafe {
@@ -0,0 +1,91 @@
+use log::{debug, info};
+use std::collections::HashSet;
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::cacheindex::{self, CacheKey};
+use crate::http::WallhavenClient;
+use crate::settings::load_settings;
+use crate::wallhaven::{api_url, Wallpaper};
+
+/// How often Wallhaven is probed while offline.
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Polls the API until it answers, then leaves offline mode. Any HTTP
+/// response below 500 counts; connection failures and server errors keep us
+/// offline.
+pub async fn probe_until_online(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(PROBE_INTERVAL).await;
+        let http = app.state::<WallhavenClient>();
+        if !http.is_offline() {
+            return;
+        }
+        let settings = load_settings(app.clone());
+        match http.http().head(api_url(&settings, "search")).send().await {
+            Ok(response) if response.status().is_server_error() => {
+                debug!("connectivity: probe got status {}", response.status())
+            }
+            Ok(_) => {
+                info!("connectivity: Wallhaven reachable again");
+                http.set_offline(false);
+                return;
+            }
+            Err(e) => debug!("connectivity: probe failed: {e}"),
+        }
+    }
+}
+
+/// Wallpapers whose full image is on disk: history first (most recent first),
+/// then the queue, then any other cached file.
+pub fn local_wallpapers(app: &tauri::AppHandle) -> Vec<Wallpaper> {
+    let index = cacheindex::snapshot(app);
+    let on_disk = |w: &Wallpaper| index.lookup(&CacheKey::for_wallpaper(w)).is_some();
+    let history = crate::history::get_history(app.clone());
+    let queue = crate::queue::get_queue(app.clone());
+
+    let mut seen = HashSet::new();
+    let mut wallpapers: Vec<Wallpaper> = history
+        .iter()
+        .map(|e| e.to_wallpaper())
+        .chain(queue)
+        .filter(|w| on_disk(w) && seen.insert(w.id.clone()))
+        .collect();
+
+    // Files without a history or queue record: rebuild the record from the index
+    let mut indexed = cacheindex::entries(app);
+    indexed.sort_by(|a, b| b.last_access.cmp(&a.last_access));
+    for entry in indexed {
+        if entry.url.is_empty() || !seen.insert(entry.id.clone()) {
+            continue;
+        }
+        wallpapers.push(Wallpaper {
+            url: match entry.source.as_str() {
+                "wallhaven" => format!("https://wallhaven.cc/w/{}", entry.id),
+                _ => entry.url.clone(),
+            },
+            path: entry.url,
+            file_size: entry.size,
+            dimension_x: entry.width,
+            dimension_y: entry.height,
+            resolution: if entry.width > 0 {
+                format!("{}x{}", entry.width, entry.height)
+            } else {
+                String::new()
+            },
+            id: entry.id,
+            ..Wallpaper::default()
+        });
+    }
+    wallpapers
+}
+
+#[tauri::command]
+pub fn get_local_wallpapers(app: tauri::AppHandle) -> Vec<Wallpaper> {
+    local_wallpapers(&app)
+}
+
+#[tauri::command]
+pub fn is_offline(http: tauri::State<'_, WallhavenClient>) -> bool {
+    http.is_offline()
+}
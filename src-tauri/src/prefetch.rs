@@ -0,0 +1,100 @@
+use log::{debug, info, warn};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+use tokio::sync::Semaphore;
+
+use crate::cacheindex::{self, CacheKey};
+use crate::download::{verify_file, DownloadManager};
+use crate::http::WallhavenClient;
+use crate::settings::load_settings;
+use crate::wallhaven::Wallpaper;
+
+/// Downloads upcoming queue and cycle wallpapers ahead of time so scheduled
+/// changes apply from the cache. Managed as Tauri state.
+pub struct Prefetcher {
+    /// Semaphore sized to the configured concurrency, rebuilt when it changes.
+    limit: Mutex<(u32, Arc<Semaphore>)>,
+    /// Ids scheduled or downloading, so repeated calls don't pile up.
+    pending: Mutex<HashSet<String>>,
+    /// The last list of upcoming wallpapers, kept safe from cache eviction.
+    upcoming: Mutex<Vec<Wallpaper>>,
+}
+
+impl Default for Prefetcher {
+    fn default() -> Self {
+        Self {
+            limit: Mutex::new((0, Arc::new(Semaphore::new(1)))),
+            pending: Mutex::new(HashSet::new()),
+            upcoming: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Prefetcher {
+    fn semaphore(&self, concurrency: u32) -> Arc<Semaphore> {
+        let mut limit = self.limit.lock().unwrap();
+        if limit.0 != concurrency {
+            *limit = (concurrency, Arc::new(Semaphore::new(concurrency.max(1) as usize)));
+        }
+        limit.1.clone()
+    }
+
+    /// The wallpapers most recently passed to [`Prefetcher::schedule`].
+    pub fn upcoming(&self) -> Vec<Wallpaper> {
+        self.upcoming.lock().unwrap().clone()
+    }
+
+    /// Schedules background downloads for the first `prefetch_count` of
+    /// `upcoming` that aren't cached yet. Returns how many were scheduled.
+    /// Nothing is prefetched offline or on a metered connection.
+    pub fn schedule(&self, app: &tauri::AppHandle, upcoming: &[Wallpaper]) -> usize {
+        *self.upcoming.lock().unwrap() = upcoming.to_vec();
+        let settings = load_settings(app.clone());
+        if settings.prefetch_count == 0
+            || settings.metered
+            || app.state::<WallhavenClient>().is_offline()
+        {
+            return 0;
+        }
+        let semaphore = self.semaphore(settings.prefetch_concurrency);
+
+        let mut scheduled = 0;
+        for wallpaper in upcoming.iter().take(settings.prefetch_count as usize) {
+            let cached = cacheindex::lookup(app, &CacheKey::for_wallpaper(wallpaper))
+                .is_some_and(|path| verify_file(&path, Some(wallpaper.file_size)).is_ok());
+            if cached || !self.pending.lock().unwrap().insert(wallpaper.id.clone()) {
+                continue;
+            }
+            scheduled += 1;
+
+            let app = app.clone();
+            let semaphore = semaphore.clone();
+            let wallpaper = wallpaper.clone();
+            tauri::async_runtime::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                debug!("prefetch: downloading {}", wallpaper.id);
+                let http = app.state::<WallhavenClient>();
+                let downloads = app.state::<DownloadManager>();
+                if let Err(e) = downloads.prefetch(&app, &http, &wallpaper).await {
+                    warn!("prefetch: {} failed: {e}", wallpaper.id);
+                }
+                app.state::<Prefetcher>().pending.lock().unwrap().remove(&wallpaper.id);
+            });
+        }
+        if scheduled > 0 {
+            info!("prefetch: scheduled {} downloads", scheduled);
+        }
+        scheduled
+    }
+}
+
+/// Prefetches the wallpapers that will be applied next, in order.
+#[tauri::command]
+pub fn prefetch_wallpapers(
+    app: tauri::AppHandle,
+    prefetcher: tauri::State<'_, Prefetcher>,
+    wallpapers: Vec<Wallpaper>,
+) -> usize {
+    prefetcher.schedule(&app, &wallpapers)
+}
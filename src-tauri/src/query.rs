@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileType {
+    Png,
+    Jpg,
+}
+
+/// One term of a Wallhaven `q` string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Term {
+    /// Bare word, fuzzily matched against tags and keywords.
+    Keyword(String),
+    /// `+tag` — results must have this tag.
+    Include(String),
+    /// `-tag` — results must not have this tag.
+    Exclude(String),
+    /// `@username` — uploads by this user.
+    User(String),
+    /// `id:123` — exact tag search. Cannot be combined with other terms.
+    TagId(u64),
+    /// `type:png` / `type:jpg`.
+    FileType(FileType),
+    /// `like:abc123` — wallpapers similar to this one.
+    Like(String),
+}
+
+/// Structured form of the `q` search parameter. Parses from and formats back
+/// to Wallhaven's query syntax, so `Query::parse(&q)?.to_string()` round-trips.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, term: Term) -> Self {
+        self.terms.push(term);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn parse(input: &str) -> Result<Self, AppError> {
+        let query = Query {
+            terms: tokenize(input)?
+                .into_iter()
+                .map(|token| parse_term(&token))
+                .collect::<Result<_, _>>()?,
+        };
+        query.validate()?;
+        Ok(query)
+    }
+
+    /// Checks constraints that span terms or that a hand-built query could break.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.terms.iter().any(|t| matches!(t, Term::TagId(_))) && self.terms.len() > 1 {
+            return Err(invalid("id:", "an exact tag search (id:N) can't be combined with other terms"));
+        }
+        for term in &self.terms {
+            match term {
+                Term::Keyword(v) | Term::Include(v) | Term::Exclude(v) if v.trim().is_empty() => {
+                    return Err(invalid(&term.to_string(), "tag name is empty"));
+                }
+                Term::Keyword(v) | Term::Include(v) | Term::Exclude(v) if v.contains('"') => {
+                    return Err(invalid(v, "tag names can't contain double quotes"));
+                }
+                Term::User(name) => validate_username(name)?,
+                Term::Like(id) => validate_wallpaper_id(id)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{term}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Keyword(v) => f.write_str(&quote(v)),
+            Term::Include(v) => write!(f, "+{}", quote(v)),
+            Term::Exclude(v) => write!(f, "-{}", quote(v)),
+            Term::User(name) => write!(f, "@{name}"),
+            Term::TagId(id) => write!(f, "id:{id}"),
+            Term::FileType(FileType::Png) => f.write_str("type:png"),
+            Term::FileType(FileType::Jpg) => f.write_str("type:jpg"),
+            Term::Like(id) => write!(f, "like:{id}"),
+        }
+    }
+}
+
+/// Multi-word tags are wrapped in double quotes so they stay one term.
+fn quote(value: &str) -> String {
+    if value.chars().any(char::is_whitespace) {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    }
+}
+
+fn invalid(token: &str, reason: &str) -> AppError {
+    AppError::parse(format!("invalid query term \"{token}\": {reason}"))
+}
+
+/// Splits on whitespace, keeping `"quoted phrases"` (optionally prefixed with
+/// `+` or `-`) together.
+fn tokenize(input: &str) -> Result<Vec<String>, AppError> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(invalid(&current, "unterminated quote"));
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn unquote(token: &str, value: &str) -> Result<String, AppError> {
+    let stripped = match value.strip_prefix('"') {
+        Some(inner) => inner
+            .strip_suffix('"')
+            .ok_or_else(|| invalid(token, "quote must wrap the whole tag"))?,
+        None if value.contains('"') => return Err(invalid(token, "quote must wrap the whole tag")),
+        None => value,
+    };
+    let stripped = stripped.trim();
+    if stripped.is_empty() {
+        return Err(invalid(token, "tag name is empty"));
+    }
+    Ok(stripped.to_string())
+}
+
+fn parse_term(token: &str) -> Result<Term, AppError> {
+    if let Some(rest) = token.strip_prefix('+') {
+        return Ok(Term::Include(unquote(token, rest)?));
+    }
+    if let Some(rest) = token.strip_prefix('-') {
+        return Ok(Term::Exclude(unquote(token, rest)?));
+    }
+    if let Some(name) = token.strip_prefix('@') {
+        validate_username(name)?;
+        return Ok(Term::User(name.to_string()));
+    }
+    if let Some((prefix, value)) = token.split_once(':') {
+        match prefix.to_ascii_lowercase().as_str() {
+            "id" => {
+                let id = value
+                    .parse()
+                    .map_err(|_| invalid(token, "tag id must be a number"))?;
+                return Ok(Term::TagId(id));
+            }
+            "type" => {
+                let file_type = match value.to_ascii_lowercase().as_str() {
+                    "png" => FileType::Png,
+                    "jpg" | "jpeg" => FileType::Jpg,
+                    _ => return Err(invalid(token, "type must be png or jpg")),
+                };
+                return Ok(Term::FileType(file_type));
+            }
+            "like" => {
+                validate_wallpaper_id(value)?;
+                return Ok(Term::Like(value.to_string()));
+            }
+            // Anything else with a colon is just a keyword
+            _ => {}
+        }
+    }
+    Ok(Term::Keyword(unquote(token, token)?))
+}
+
+pub fn validate_username(name: &str) -> Result<(), AppError> {
+    if name.is_empty() {
+        return Err(invalid("@", "username is empty"));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.') {
+        return Err(invalid(&format!("@{name}"), "username has invalid characters"));
+    }
+    Ok(())
+}
+
+fn validate_wallpaper_id(id: &str) -> Result<(), AppError> {
+    if id.is_empty() {
+        return Err(invalid("like:", "wallpaper id is empty"));
+    }
+    if !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(invalid(&format!("like:{id}"), "wallpaper id must be alphanumeric"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn parse_query(query: String) -> Result<Query, AppError> {
+    Query::parse(&query)
+}
+
+#[tauri::command]
+pub fn format_query(query: Query) -> Result<String, AppError> {
+    query.validate()?;
+    Ok(query.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_term_kind() {
+        let query = Query::parse(r#"sunset +anime -"digital art" @bob_1 type:jpeg like:abc123"#).unwrap();
+        assert_eq!(
+            query.terms,
+            vec![
+                Term::Keyword("sunset".into()),
+                Term::Include("anime".into()),
+                Term::Exclude("digital art".into()),
+                Term::User("bob_1".into()),
+                Term::FileType(FileType::Jpg),
+                Term::Like("abc123".into()),
+            ]
+        );
+        assert_eq!(Query::parse("id:42").unwrap().terms, vec![Term::TagId(42)]);
+        // Unknown prefixes are plain keywords
+        assert_eq!(Query::parse("foo:bar").unwrap().terms, vec![Term::Keyword("foo:bar".into())]);
+        assert!(Query::parse("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for q in [
+            r#"+anime -"digital art" @bob_1 type:png"#,
+            "id:123",
+            "like:abc123",
+            "sunset beach",
+            "sci-fi",
+            "foo:bar",
+        ] {
+            assert_eq!(Query::parse(q).unwrap().to_string(), q);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_terms() {
+        for bad in [
+            "id:abc",
+            "id:1 +x",
+            "type:gif",
+            "\"open",
+            "+",
+            "@",
+            "@bad!name",
+            "like:",
+            "like:a-b",
+            "-\"a\"b",
+        ] {
+            assert!(Query::parse(bad).is_err(), "{bad} should not parse");
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use std::fs;
 use tauri::Manager;
 
-use crate::wallhaven::Wallpaper;
+use crate::error::AppError;
+use crate::http::WallhavenClient;
+use crate::settings::load_settings;
+use crate::wallhaven::{ensure_uploader_allowed, Wallpaper};
 
 fn queue_path(app: &tauri::AppHandle) -> std::path::PathBuf {
     let dir = app
@@ -20,10 +23,10 @@ fn load_queue_entries(app: &tauri::AppHandle) -> Vec<Wallpaper> {
         .unwrap_or_default()
 }
 
-fn save_queue_entries(app: &tauri::AppHandle, entries: &[Wallpaper]) -> Result<(), String> {
+fn save_queue_entries(app: &tauri::AppHandle, entries: &[Wallpaper]) -> Result<(), AppError> {
     let path = queue_path(app);
-    let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
-    fs::write(&path, json).map_err(|e| e.to_string())
+    let json = serde_json::to_string_pretty(entries)?;
+    Ok(fs::write(&path, json)?)
 }
 
 #[tauri::command]
@@ -32,28 +35,33 @@ pub fn get_queue(app: tauri::AppHandle) -> Vec<Wallpaper> {
 }
 
 #[tauri::command]
-pub fn add_to_queue(app: tauri::AppHandle, wallpaper: Wallpaper) -> Result<(), String> {
+pub async fn add_to_queue(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    wallpaper: Wallpaper,
+) -> Result<(), AppError> {
     let mut entries = load_queue_entries(&app);
     if entries.iter().any(|e| e.id == wallpaper.id) {
         return Ok(());
     }
+    ensure_uploader_allowed(&http, &load_settings(app.clone()), &wallpaper).await?;
     entries.push(wallpaper);
     save_queue_entries(&app, &entries)
 }
 
 #[tauri::command]
-pub fn remove_from_queue(app: tauri::AppHandle, wallpaper_id: String) -> Result<(), String> {
+pub fn remove_from_queue(app: tauri::AppHandle, wallpaper_id: String) -> Result<(), AppError> {
     let mut entries = load_queue_entries(&app);
     entries.retain(|e| e.id != wallpaper_id);
     save_queue_entries(&app, &entries)
 }
 
 #[tauri::command]
-pub fn reorder_queue(app: tauri::AppHandle, wallpapers: Vec<Wallpaper>) -> Result<(), String> {
+pub fn reorder_queue(app: tauri::AppHandle, wallpapers: Vec<Wallpaper>) -> Result<(), AppError> {
     save_queue_entries(&app, &wallpapers)
 }
 
 #[tauri::command]
-pub fn clear_queue(app: tauri::AppHandle) -> Result<(), String> {
+pub fn clear_queue(app: tauri::AppHandle) -> Result<(), AppError> {
     save_queue_entries(&app, &[])
 }
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sorting {
+    DateAdded,
+    Relevance,
+    Random,
+    Views,
+    Favorites,
+    Toplist,
+    Hot,
+}
+
+impl Sorting {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Sorting::DateAdded => "date_added",
+            Sorting::Relevance => "relevance",
+            Sorting::Random => "random",
+            Sorting::Views => "views",
+            Sorting::Favorites => "favorites",
+            Sorting::Toplist => "toplist",
+            Sorting::Hot => "hot",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    Desc,
+    Asc,
+}
+
+impl Order {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Order::Desc => "desc",
+            Order::Asc => "asc",
+        }
+    }
+}
+
+/// Time window for `sorting=toplist`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TopRange {
+    #[serde(rename = "1d")]
+    Day,
+    #[serde(rename = "3d")]
+    ThreeDays,
+    #[serde(rename = "1w")]
+    Week,
+    #[serde(rename = "1M")]
+    Month,
+    #[serde(rename = "3M")]
+    ThreeMonths,
+    #[serde(rename = "6M")]
+    SixMonths,
+    #[serde(rename = "1y")]
+    Year,
+}
+
+impl TopRange {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TopRange::Day => "1d",
+            TopRange::ThreeDays => "3d",
+            TopRange::Week => "1w",
+            TopRange::Month => "1M",
+            TopRange::ThreeMonths => "3M",
+            TopRange::SixMonths => "6M",
+            TopRange::Year => "1y",
+        }
+    }
+}
+
+/// Every parameter accepted by Wallhaven's `/search` endpoint. `None` means
+/// "not specified", so a request's params can be layered over the defaults
+/// stored in Settings with [`SearchParams::merged_with`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchParams {
+    pub q: Option<String>,
+    /// Bit string of general/anime/people, e.g. `"110"`.
+    pub categories: Option<String>,
+    /// Bit string of sfw/sketchy/nsfw, e.g. `"100"`.
+    pub purity: Option<String>,
+    pub sorting: Option<Sorting>,
+    pub order: Option<Order>,
+    pub top_range: Option<TopRange>,
+    /// Minimum resolution, e.g. `"1920x1080"`.
+    pub atleast: Option<String>,
+    /// Exact resolutions, e.g. `["1920x1080", "2560x1440"]`.
+    pub resolutions: Option<Vec<String>>,
+    /// Aspect ratios, e.g. `["16x9", "21x9"]`.
+    pub ratios: Option<Vec<String>>,
+    /// Hex colour without the leading `#`, e.g. `"663399"`.
+    pub colors: Option<String>,
+    /// `true` hides AI-generated art.
+    pub ai_art_filter: Option<bool>,
+    pub page: Option<u32>,
+    pub seed: Option<String>,
+}
+
+impl SearchParams {
+    /// Returns `self` with every field that `overrides` specifies replaced.
+    pub fn merged_with(&self, overrides: &SearchParams) -> SearchParams {
+        SearchParams {
+            q: overrides.q.clone().or_else(|| self.q.clone()),
+            categories: overrides.categories.clone().or_else(|| self.categories.clone()),
+            purity: overrides.purity.clone().or_else(|| self.purity.clone()),
+            sorting: overrides.sorting.or(self.sorting),
+            order: overrides.order.or(self.order),
+            top_range: overrides.top_range.or(self.top_range),
+            atleast: overrides.atleast.clone().or_else(|| self.atleast.clone()),
+            resolutions: overrides.resolutions.clone().or_else(|| self.resolutions.clone()),
+            ratios: overrides.ratios.clone().or_else(|| self.ratios.clone()),
+            colors: overrides.colors.clone().or_else(|| self.colors.clone()),
+            ai_art_filter: overrides.ai_art_filter.or(self.ai_art_filter),
+            page: overrides.page.or(self.page),
+            seed: overrides.seed.clone().or_else(|| self.seed.clone()),
+        }
+    }
+
+    /// Query pairs using Wallhaven's parameter names. Empty values are skipped.
+    pub fn to_query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        let mut push = |key: &'static str, value: Option<String>| {
+            if let Some(v) = value.filter(|v| !v.trim().is_empty()) {
+                query.push((key, v));
+            }
+        };
+        let join = |list: &Option<Vec<String>>| {
+            list.as_ref().map(|items| {
+                items
+                    .iter()
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+        };
+
+        push("q", self.q.clone());
+        push("categories", self.categories.clone());
+        push("purity", self.purity.clone());
+        push("sorting", self.sorting.map(|s| s.as_str().to_string()));
+        push("order", self.order.map(|o| o.as_str().to_string()));
+        // topRange is only meaningful for the toplist
+        if self.sorting == Some(Sorting::Toplist) {
+            push("topRange", self.top_range.map(|t| t.as_str().to_string()));
+        }
+        push("atleast", self.atleast.clone());
+        push("resolutions", join(&self.resolutions));
+        push("ratios", join(&self.ratios));
+        push("colors", self.colors.as_ref().map(|c| c.trim_start_matches('#').to_string()));
+        push("ai_art_filter", self.ai_art_filter.map(|f| if f { "1" } else { "0" }.to_string()));
+        push("page", self.page.map(|p| p.to_string()));
+        push("seed", self.seed.clone());
+        query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value<'a>(query: &'a [(&str, String)], key: &str) -> Option<&'a str> {
+        query.iter().find(|(k, _)| *k == key).map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn top_range_only_for_toplist() {
+        let mut params = SearchParams {
+            sorting: Some(Sorting::Hot),
+            top_range: Some(TopRange::Week),
+            ..SearchParams::default()
+        };
+        assert_eq!(value(&params.to_query(), "topRange"), None);
+        params.sorting = Some(Sorting::Toplist);
+        assert_eq!(value(&params.to_query(), "topRange"), Some("1w"));
+    }
+
+    #[test]
+    fn formats_lists_and_skips_empty_values() {
+        let params = SearchParams {
+            q: Some(" ".into()),
+            resolutions: Some(vec!["1920x1080".into(), " ".into(), " 2560x1440".into()]),
+            ratios: Some(vec![]),
+            colors: Some("#663399".into()),
+            ai_art_filter: Some(true),
+            page: Some(2),
+            ..SearchParams::default()
+        };
+        let query = params.to_query();
+        assert_eq!(value(&query, "q"), None);
+        assert_eq!(value(&query, "resolutions"), Some("1920x1080,2560x1440"));
+        assert_eq!(value(&query, "ratios"), None);
+        assert_eq!(value(&query, "colors"), Some("663399"));
+        assert_eq!(value(&query, "ai_art_filter"), Some("1"));
+        assert_eq!(value(&query, "page"), Some("2"));
+    }
+}
@@ -0,0 +1,175 @@
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::download::cache_dir;
+use crate::error::AppError;
+use crate::search::Sorting;
+use crate::wallhaven::WallpaperPage;
+
+/// Result pages untouched for this long are deleted at startup.
+const MAX_STALE_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Minutes a cached result page stays fresh, per sorting. 0 disables caching
+/// for that sorting. `random` is never cached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResultTtl {
+    pub date_added: u64,
+    pub relevance: u64,
+    pub views: u64,
+    pub favorites: u64,
+    pub toplist: u64,
+    pub hot: u64,
+    pub collection: u64,
+}
+
+impl Default for ResultTtl {
+    fn default() -> Self {
+        Self {
+            date_added: 10,
+            relevance: 30,
+            views: 60,
+            favorites: 60,
+            toplist: 360,
+            hot: 30,
+            collection: 30,
+        }
+    }
+}
+
+impl ResultTtl {
+    /// `None` means the results must not be cached at all.
+    pub fn for_sorting(&self, sorting: Option<Sorting>) -> Option<Duration> {
+        let minutes = match sorting.unwrap_or(Sorting::DateAdded) {
+            Sorting::Random => return None,
+            Sorting::DateAdded => self.date_added,
+            Sorting::Relevance => self.relevance,
+            Sorting::Views => self.views,
+            Sorting::Favorites => self.favorites,
+            Sorting::Toplist => self.toplist,
+            Sorting::Hot => self.hot,
+        };
+        minutes_to_ttl(minutes)
+    }
+
+    pub fn for_collection(&self) -> Option<Duration> {
+        minutes_to_ttl(self.collection)
+    }
+}
+
+fn minutes_to_ttl(minutes: u64) -> Option<Duration> {
+    (minutes > 0).then(|| Duration::from_secs(minutes * 60))
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedPage {
+    /// RFC 3339.
+    cached_at: String,
+    page: WallpaperPage,
+}
+
+/// Cache key for a request: the endpoint, every query pair (page included)
+/// and which API key it was sent with, since the account decides what purity
+/// and blacklists allow. Only a fingerprint of the key is hashed in.
+pub fn key(url: &str, query: &[(&str, String)], api_key: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(url.as_bytes());
+    for (name, value) in query {
+        hasher.update(format!("\n{name}={value}").as_bytes());
+    }
+    match api_key {
+        Some(api_key) => {
+            let fingerprint = Sha256::digest(api_key.as_bytes());
+            hasher.update(b"\nauth=");
+            hasher.update(&fingerprint[..8]);
+        }
+        None => hasher.update(b"\nanon"),
+    }
+    hasher.finalize()[..16].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn results_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let dir = cache_dir(app)?.join("results");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// The cached page and its age.
+fn read(app: &tauri::AppHandle, key: &str) -> Option<(WallpaperPage, Duration)> {
+    let path = results_dir(app).ok()?.join(format!("{key}.json"));
+    let cached: CachedPage = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let cached_at = chrono::DateTime::parse_from_rfc3339(&cached.cached_at).ok()?;
+    let age = (chrono::Utc::now() - cached_at.with_timezone(&chrono::Utc)).to_std().unwrap_or_default();
+    Some((cached.page, age))
+}
+
+fn write(app: &tauri::AppHandle, key: &str, page: &WallpaperPage) -> Result<(), AppError> {
+    let path = results_dir(app)?.join(format!("{key}.json"));
+    let cached = CachedPage {
+        cached_at: chrono::Utc::now().to_rfc3339(),
+        page: page.clone(),
+    };
+    Ok(fs::write(path, serde_json::to_string(&cached)?)?)
+}
+
+/// Serves a fresh cached page if there is one, otherwise runs `fetch` and
+/// caches its result. When `fetch` fails because the network is down, a
+/// cached page of any age is returned with `stale` set. `ttl` of `None`
+/// bypasses the cache entirely.
+pub async fn cached(
+    app: &tauri::AppHandle,
+    key: &str,
+    ttl: Option<Duration>,
+    fetch: impl Future<Output = Result<WallpaperPage, AppError>>,
+) -> Result<WallpaperPage, AppError> {
+    let Some(ttl) = ttl else {
+        return fetch.await;
+    };
+    if let Some((page, age)) = read(app, key).filter(|(_, age)| *age < ttl) {
+        debug!("searchcache: hit {} ({}s old)", key, age.as_secs());
+        return Ok(page);
+    }
+
+    match fetch.await {
+        Ok(page) => {
+            if let Err(e) = write(app, key, &page) {
+                warn!("searchcache: couldn't store {}: {e}", key);
+            }
+            Ok(page)
+        }
+        Err(e) if e.is_offline() => match read(app, key) {
+            Some((mut page, age)) => {
+                warn!("searchcache: network failed ({e}), serving {}s old results", age.as_secs());
+                page.stale = true;
+                Ok(page)
+            }
+            None => Err(e),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Deletes result pages too old to be worth serving even when offline.
+pub fn prune(app: &tauri::AppHandle) -> Result<(), AppError> {
+    let mut removed = 0;
+    for item in fs::read_dir(results_dir(app)?)? {
+        let path = item?.path();
+        let too_old = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > MAX_STALE_AGE);
+        if too_old && fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        info!("searchcache: pruned {} old result pages", removed);
+    }
+    Ok(())
+}
@@ -2,7 +2,12 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use tauri::Manager;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::error::AppError;
+use crate::search::SearchParams;
+use crate::searchcache::ResultTtl;
+use crate::setwallpaper::{FitMode, SwwwOptions};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub username: String,
     pub api_key: String,
@@ -20,10 +25,66 @@ pub struct Settings {
     pub collection_cycle_interval_minutes: u32,
     #[serde(default)]
     pub linux_wallpaper_cmd: String,
+    /// Id of the wallpaper backend to always use. Empty picks one automatically.
+    #[serde(default)]
+    pub wallpaper_backend: String,
+    /// How backends that support it scale the image.
+    #[serde(default)]
+    pub wallpaper_fit: FitMode,
+    /// Output to set the wallpaper on, e.g. `DP-1`. Empty sets every monitor.
+    #[serde(default)]
+    pub wallpaper_monitor: String,
+    /// Transition used by the swww backend.
+    #[serde(default)]
+    pub swww: SwwwOptions,
     #[serde(default = "default_thumb_size")]
     pub thumb_size: String,
     #[serde(default = "default_hotkey_modifier")]
     pub hotkey_modifier: String,
+    /// Overrides the Wallhaven API base (e.g. a caching proxy). Empty means the public API.
+    #[serde(default)]
+    pub api_base_url: String,
+    /// Overrides the host that full images and thumbnails are downloaded from.
+    #[serde(default)]
+    pub image_base_url: String,
+    /// Defaults for search parameters not covered by the filter fields above
+    /// (order, toplist range, colours, exact resolutions, AI art filter).
+    #[serde(default)]
+    pub search_defaults: SearchParams,
+    /// Tags excluded from every search, imported from the Wallhaven account.
+    #[serde(default)]
+    pub tag_blacklist: Vec<String>,
+    /// Uploaders blacklisted on the Wallhaven account.
+    #[serde(default)]
+    pub user_blacklist: Vec<String>,
+    /// Size cap for cached full-size images in MB. 0 disables the cap.
+    #[serde(default = "default_cache_max_mb")]
+    pub cache_max_mb: u64,
+    /// Cached images not used for this many days are removed. 0 keeps them forever.
+    #[serde(default)]
+    pub cache_max_age_days: u32,
+    /// How many upcoming queue/cycle wallpapers to download ahead. 0 disables prefetching.
+    #[serde(default = "default_prefetch_count")]
+    pub prefetch_count: u32,
+    /// Maximum prefetch downloads running at once.
+    #[serde(default = "default_prefetch_concurrency")]
+    pub prefetch_concurrency: u32,
+    /// How long cached search and collection pages stay fresh.
+    #[serde(default)]
+    pub result_ttl: ResultTtl,
+    /// Metered connection: no prefetching, smaller images preferred and
+    /// downloads refused once the bandwidth budget is spent.
+    #[serde(default)]
+    pub metered: bool,
+    /// Daily download budget for full-size images in MB. 0 is unlimited.
+    #[serde(default)]
+    pub bandwidth_daily_mb: u64,
+    /// Monthly download budget for full-size images in MB. 0 is unlimited.
+    #[serde(default)]
+    pub bandwidth_monthly_mb: u64,
+    /// In metered mode, cycling skips images larger than this when it can. 0 disables.
+    #[serde(default = "default_metered_max_file_mb")]
+    pub metered_max_file_mb: u64,
 }
 
 fn default_purity() -> String {
@@ -46,6 +107,22 @@ fn default_hotkey_modifier() -> String {
     "meta".to_string()
 }
 
+fn default_cache_max_mb() -> u64 {
+    2048
+}
+
+fn default_prefetch_count() -> u32 {
+    3
+}
+
+fn default_prefetch_concurrency() -> u32 {
+    2
+}
+
+fn default_metered_max_file_mb() -> u64 {
+    3
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
@@ -58,12 +135,47 @@ impl Default for Settings {
             collection_cycle_collection_id: 0,
             collection_cycle_interval_minutes: default_collection_cycle_interval(),
             linux_wallpaper_cmd: String::new(),
+            wallpaper_backend: String::new(),
+            wallpaper_fit: FitMode::default(),
+            wallpaper_monitor: String::new(),
+            swww: SwwwOptions::default(),
             thumb_size: default_thumb_size(),
             hotkey_modifier: default_hotkey_modifier(),
+            api_base_url: String::new(),
+            image_base_url: String::new(),
+            search_defaults: SearchParams::default(),
+            tag_blacklist: Vec::new(),
+            user_blacklist: Vec::new(),
+            cache_max_mb: default_cache_max_mb(),
+            cache_max_age_days: 0,
+            prefetch_count: default_prefetch_count(),
+            prefetch_concurrency: default_prefetch_concurrency(),
+            result_ttl: ResultTtl::default(),
+            metered: false,
+            bandwidth_daily_mb: 0,
+            bandwidth_monthly_mb: 0,
+            metered_max_file_mb: default_metered_max_file_mb(),
         }
     }
 }
 
+impl Settings {
+    /// Default search parameters for every request. The purity, categories,
+    /// atleast and ratios fields take precedence over `search_defaults`.
+    pub fn search_params(&self) -> SearchParams {
+        let non_empty = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+        let filters = SearchParams {
+            purity: non_empty(&self.purity),
+            categories: non_empty(&self.categories),
+            atleast: non_empty(&self.atleast),
+            ratios: non_empty(&self.ratios)
+                .map(|r| r.split(',').map(|s| s.trim().to_string()).collect()),
+            ..SearchParams::default()
+        };
+        self.search_defaults.merged_with(&filters)
+    }
+}
+
 fn settings_path(app: &tauri::AppHandle) -> std::path::PathBuf {
     let dir = app
         .path()
@@ -83,8 +195,8 @@ pub fn load_settings(app: tauri::AppHandle) -> Settings {
 }
 
 #[tauri::command]
-pub fn save_settings(app: tauri::AppHandle, settings: Settings) -> Result<(), String> {
+pub fn save_settings(app: tauri::AppHandle, settings: Settings) -> Result<(), AppError> {
     let path = settings_path(&app);
-    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
-    fs::write(&path, json).map_err(|e| e.to_string())
+    let json = serde_json::to_string_pretty(&settings)?;
+    Ok(fs::write(&path, json)?)
 }
@@ -1,180 +1,258 @@
-use std::path::Path;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
 
-pub fn set(path: &str, custom_cmd: &str) -> Result<(), String> {
-    let path = Path::new(path);
-    if !path.exists() {
-        return Err(format!("file does not exist: {}", path.display()));
-    }
+use crate::error::AppError;
+use crate::settings::{load_settings, Settings};
 
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e.to_lowercase())
-        .unwrap_or_default();
+#[cfg(target_os = "linux")]
+mod desktop;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+mod wlroots;
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
 
-    match ext.as_str() {
-        "jpg" | "jpeg" | "png" | "gif" | "bmp" => {}
-        _ => return Err(format!("unsupported filetype: .{ext}")),
-    }
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(target_os = "windows")]
+use windows as platform;
 
-    platform::set_wallpaper(path.to_str().unwrap(), custom_cmd)
+/// How the image is scaled to the screen. Backends map this onto their own
+/// flags; ones that can't express a mode fall back to the closest they have.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitMode {
+    /// Scale to cover the screen, cropping the overflow.
+    #[default]
+    Fill,
+    /// Scale to fit inside the screen, leaving borders.
+    Fit,
+    /// Scale to the screen size, ignoring aspect ratio.
+    Stretch,
+    Center,
+    Tile,
 }
 
-#[cfg(target_os = "macos")]
-mod platform {
-    use objc::runtime::Object;
-    use std::ffi::CString;
-
-    pub fn set_wallpaper(path: &str, _custom_cmd: &str) -> Result<(), String> {
-        // Count attached screens first (fast, no alloc needed)
-        let count: usize = unsafe {
-            let screens: *mut Object = msg_send![class!(NSScreen), screens];
-            msg_send![screens, count]
-        };
+/// Transition settings passed to `swww img`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwwwOptions {
+    /// `simple`, `fade`, `wipe`, `grow`, `outer`, `wave`, `random`, …
+    pub transition_type: String,
+    /// Seconds.
+    pub transition_duration: f32,
+    pub transition_fps: u32,
+    /// Degrees, for `wipe` and `wave`.
+    pub transition_angle: u32,
+    /// Where `grow` and `outer` start, e.g. `center` or `0.5,0.5`. Empty uses swww's default.
+    pub transition_pos: String,
+}
 
-        if count == 0 {
-            return Err("no screens found".to_string());
+impl Default for SwwwOptions {
+    fn default() -> Self {
+        Self {
+            transition_type: "fade".to_string(),
+            transition_duration: 1.0,
+            transition_fps: 60,
+            transition_angle: 45,
+            transition_pos: String::new(),
         }
+    }
+}
 
-        // macOS serialises setDesktopImageURL calls internally (one per screen,
-        // ~5 s each). Spawn a detached thread per screen and return immediately
-        // so the UI isn't blocked — the wallpaper applies in the background.
-        // The file is already verified to exist before this function is called.
-        for i in 0..count {
-            let path = path.to_owned();
-            std::thread::spawn(move || {
-                let Ok(path_c) = CString::new(path.as_str()) else { return };
-                unsafe {
-                    let alloc: *mut Object = msg_send![class!(NSString), alloc];
-                    let path_ns: *mut Object =
-                        msg_send![alloc, initWithUTF8String: path_c.as_ptr()];
-                    let url: *mut Object =
-                        msg_send![class!(NSURL), fileURLWithPath: path_ns];
-                    let options: *mut Object =
-                        msg_send![class!(NSDictionary), dictionary];
-                    let workspace: *mut Object =
-                        msg_send![class!(NSWorkspace), sharedWorkspace];
-                    let screens: *mut Object =
-                        msg_send![class!(NSScreen), screens];
-                    let screen: *mut Object =
-                        msg_send![screens, objectAtIndex: i];
-
-                    let _ok: bool = msg_send![
-                        workspace,
-                        setDesktopImageURL: url
-                        forScreen: screen
-                        options: options
-                        error: std::ptr::null_mut::<*mut Object>()
-                    ];
-
-                    let _: () = msg_send![path_ns, release];
-                }
-            });
+/// Whether a backend can run in this session, and why.
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub available: bool,
+    /// Shown in the backend list, e.g. "DESKTOP_SESSION=gnome, gsettings found".
+    pub reason: String,
+}
+
+impl Detection {
+    pub fn available(reason: impl Into<String>) -> Self {
+        Self {
+            available: true,
+            reason: reason.into(),
         }
+    }
 
-        Ok(())
+    pub fn unavailable(reason: impl Into<String>) -> Self {
+        Self {
+            available: false,
+            reason: reason.into(),
+        }
     }
 }
 
-#[cfg(target_os = "linux")]
-mod platform {
-    use std::env;
-    use std::process::Command;
-
-    pub fn set_wallpaper(path: &str, custom_cmd: &str) -> Result<(), String> {
-        if !custom_cmd.is_empty() {
-            let mut parts = custom_cmd.split_whitespace();
-            let prog = parts.next().ok_or("empty wallpaper command")?;
-            let output = Command::new(prog)
-                .args(parts)
-                .arg(path)
-                .output()
-                .map_err(|e| format!("failed to run wallpaper command: {e}"))?;
-            if !output.status.success() {
-                return Err(format!(
-                    "wallpaper command failed: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
-            return Ok(());
-        }
-        let desktop = env::var("DESKTOP_SESSION").unwrap_or_default();
-        let cmd = match desktop.as_str() {
-            "plasma" => format!(
-                "dbus-send --session --dest=org.kde.plasmashell --type=method_call /PlasmaShell org.kde.PlasmaShell.evaluateScript string:\"var allDesktops = desktops(); for (i = 0; i < allDesktops.length; i++) {{ d = allDesktops[i]; d.wallpaperPlugin = 'org.kde.image'; d.currentConfigGroup = Array('Wallpaper', 'org.kde.image', 'General'); d.writeConfig('Image', 'file://{path}'); }}\""
-            ),
-            "gnome" | "gnome-wayland" | "ubuntu" => format!(
-                "gsettings set org.gnome.desktop.background picture-uri file://{path} && gsettings set org.gnome.desktop.background picture-uri-dark file://{path}"
-            ),
-            "cinnamon" => format!(
-                "gsettings set org.cinnamon.desktop.background picture-uri file://{path}"
-            ),
-            "mate" => format!(
-                "gsettings set org.mate.background picture-filename \"{path}\""
-            ),
-            "budgie-desktop" => format!(
-                "gsettings set org.gnome.desktop.background picture-uri \"file://{path}\""
-            ),
-            "xfce" => format!(
-                "for prop in $(xfconf-query -c xfce4-desktop -l | grep last-image); do xfconf-query -c xfce4-desktop -p $prop -s '{path}'; done"
-            ),
-            _ => return Err(format!("unsupported desktop environment: {desktop}. Set a wallpaper command in Settings.")),
-        };
+/// One way of setting the desktop wallpaper: a desktop environment's own
+/// settings, a standalone tool, or an OS API.
+pub trait WallpaperSetter: Send + Sync {
+    /// Stable identifier stored in `Settings::wallpaper_backend`.
+    fn id(&self) -> &'static str;
 
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(&cmd)
-            .output()
-            .map_err(|e| format!("failed to run command: {e}"))?;
-
-        if !output.status.success() {
-            return Err(format!(
-                "set wallpaper failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
-        Ok(())
+    fn name(&self) -> &'static str;
+
+    /// Probes env vars and `PATH` to decide whether the backend fits the running session.
+    fn detect(&self) -> Detection;
+
+    /// Sets `path` as the wallpaper on `monitor`, or on every monitor when `None`.
+    fn apply(&self, path: &Path, monitor: Option<&str>) -> Result<(), AppError>;
+
+    /// The wallpaper currently set, when the backend can read it back.
+    fn current(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Whether `apply` honours its `monitor` argument.
+    fn supports_per_monitor(&self) -> bool {
+        false
     }
 }
 
-#[cfg(target_os = "windows")]
-mod platform {
-    use std::ffi::OsStr;
-    use std::os::windows::ffi::OsStrExt;
-
-    #[link(name = "user32")]
-    extern "system" {
-        fn SystemParametersInfoW(
-            uiAction: u32,
-            uiParam: u32,
-            pvParam: *const u16,
-            fWinIni: u32,
-        ) -> i32;
+/// A backend and its detection result. Detection spawns processes and scans
+/// `/proc`, so it runs at most once, and only when asked for.
+struct Candidate {
+    backend: Box<dyn WallpaperSetter>,
+    detection: OnceCell<Detection>,
+}
+
+impl Candidate {
+    fn detection(&self) -> &Detection {
+        self.detection.get_or_init(|| self.backend.detect())
     }
+}
 
-    const SPI_SETDESKWALLPAPER: u32 = 0x0014;
-    const SPIF_UPDATEINIFILE: u32 = 0x01;
-    const SPIF_SENDCHANGE: u32 = 0x02;
-
-    pub fn set_wallpaper(path: &str, _custom_cmd: &str) -> Result<(), String> {
-        let wide: Vec<u16> = OsStr::new(path)
-            .encode_wide()
-            .chain(std::iter::once(0))
-            .collect();
-
-        let result = unsafe {
-            SystemParametersInfoW(
-                SPI_SETDESKWALLPAPER,
-                0,
-                wide.as_ptr(),
-                SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
-            )
+/// Every backend for this platform, in the order they're tried when picking
+/// one automatically.
+fn candidates(settings: &Settings) -> Vec<Candidate> {
+    platform::backends(settings)
+        .into_iter()
+        .map(|backend| Candidate {
+            backend,
+            detection: OnceCell::new(),
+        })
+        .collect()
+}
+
+/// Index of the forced backend from settings, else of the first one detected.
+fn choose(candidates: &[Candidate], settings: &Settings) -> Result<usize, AppError> {
+    let forced = settings.wallpaper_backend.trim();
+    if !forced.is_empty() {
+        let Some(index) = candidates.iter().position(|c| c.backend.id() == forced) else {
+            return Err(AppError::invalid_input(format!("unknown wallpaper backend: {forced}")));
         };
+        let detection = candidates[index].detection();
+        if !detection.available {
+            warn!("setwallpaper: forced backend {} may not work: {}", forced, detection.reason);
+        }
+        return Ok(index);
+    }
+
+    if let Some(index) = candidates.iter().position(|c| c.detection().available) {
+        let candidate = &candidates[index];
+        info!("setwallpaper: using {} ({})", candidate.backend.id(), candidate.detection().reason);
+        return Ok(index);
+    }
+    let reasons: Vec<String> = candidates
+        .iter()
+        .map(|c| format!("{}: {}", c.backend.id(), c.detection().reason))
+        .collect();
+    Err(AppError::unsupported_desktop(format!(
+        "no wallpaper backend detected ({}). Pick one or set a wallpaper command in Settings.",
+        reasons.join("; ")
+    )))
+}
+
+fn select(settings: &Settings) -> Result<Box<dyn WallpaperSetter>, AppError> {
+    let mut candidates = candidates(settings);
+    let index = choose(&candidates, settings)?;
+    Ok(candidates.swap_remove(index).backend)
+}
+
+/// Sets `path` on the monitor named in `Settings::wallpaper_monitor`, or on
+/// every monitor when that is empty or the backend can't target one. Backends
+/// spawn processes and may wait on them, so this runs on the blocking pool.
+pub async fn set(path: PathBuf, settings: Settings) -> Result<(), AppError> {
+    tauri::async_runtime::spawn_blocking(move || set_blocking(&path, &settings))
+        .await
+        .map_err(|e| AppError::setter_failed(format!("wallpaper setter panicked: {e}")))?
+}
+
+fn set_blocking(path: &Path, settings: &Settings) -> Result<(), AppError> {
+    if !path.exists() {
+        return Err(AppError::not_found(format!("file does not exist: {}", path.display())));
+    }
+
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" => {}
+        _ => return Err(AppError::invalid_input(format!("unsupported filetype: .{ext}"))),
+    }
 
-        if result == 0 {
-            Err("SystemParametersInfoW failed".into())
-        } else {
-            Ok(())
+    let backend = select(settings)?;
+    let monitor = Some(settings.wallpaper_monitor.trim()).filter(|m| !m.is_empty());
+    match monitor {
+        Some(name) if !backend.supports_per_monitor() => {
+            warn!("setwallpaper: {} can't target {}, setting every monitor", backend.id(), name);
+            backend.apply(path, None)
         }
+        _ => backend.apply(path, monitor),
     }
 }
+
+#[derive(Debug, Serialize)]
+pub struct BackendInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub available: bool,
+    pub reason: String,
+    pub per_monitor: bool,
+    /// The backend `set_wallpaper` would use right now.
+    pub selected: bool,
+}
+
+/// Every backend with its detection result, so the UI can show why one was chosen.
+#[tauri::command]
+pub fn list_wallpaper_backends(app: tauri::AppHandle) -> Vec<BackendInfo> {
+    let settings = load_settings(app);
+    let candidates = candidates(&settings);
+    let selected = choose(&candidates, &settings).ok();
+    candidates
+        .iter()
+        .enumerate()
+        .map(|(index, candidate)| {
+            let detection = candidate.detection();
+            BackendInfo {
+                id: candidate.backend.id(),
+                name: candidate.backend.name(),
+                available: detection.available,
+                reason: detection.reason.clone(),
+                per_monitor: candidate.backend.supports_per_monitor(),
+                selected: selected == Some(index),
+            }
+        })
+        .collect()
+}
+
+/// The wallpaper the selected backend reports as current, if it can tell.
+#[tauri::command]
+pub fn get_desktop_wallpaper(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    let settings = load_settings(app);
+    Ok(select(&settings)?
+        .current()
+        .map(|path| path.to_string_lossy().into_owned()))
+}
@@ -0,0 +1,210 @@
+// Works out which desktop environment is running. `XDG_CURRENT_DESKTOP` is
+// the most reliable source, then the session name, then the processes each
+// desktop is known to run.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Desktop {
+    Kde,
+    Gnome,
+    Cinnamon,
+    Mate,
+    Budgie,
+    Xfce,
+    Lxqt,
+    Pantheon,
+    Deepin,
+    Cosmic,
+}
+
+impl Desktop {
+    /// Maps one `XDG_CURRENT_DESKTOP` entry or session name, e.g. `KDE`,
+    /// `plasmawayland`, `ubuntu-xorg`, `X-Cinnamon` or `budgie-desktop`.
+    /// Pop!_OS names its session `pop` under both GNOME and COSMIC, so
+    /// `cosmic` says which one is running.
+    fn from_name(name: &str, cosmic: bool) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        let name = name.strip_prefix("x-").unwrap_or(&name);
+        let starts = |prefixes: &[&str]| prefixes.iter().any(|p| name.starts_with(p));
+        Some(if starts(&["kde", "plasma"]) {
+            Self::Kde
+        } else if starts(&["budgie"]) {
+            Self::Budgie
+        } else if starts(&["cinnamon"]) {
+            Self::Cinnamon
+        } else if starts(&["mate"]) {
+            Self::Mate
+        } else if starts(&["xfce", "xubuntu"]) {
+            Self::Xfce
+        } else if starts(&["lxqt", "lubuntu"]) {
+            Self::Lxqt
+        } else if starts(&["pantheon"]) {
+            Self::Pantheon
+        } else if starts(&["deepin", "dde"]) {
+            Self::Deepin
+        } else if starts(&["cosmic"]) || (cosmic && starts(&["pop"])) {
+            Self::Cosmic
+        } else if starts(&["gnome", "ubuntu", "unity", "pop", "zorin"]) {
+            Self::Gnome
+        } else {
+            return None;
+        })
+    }
+
+    /// A process only this desktop runs.
+    fn process(self) -> &'static str {
+        match self {
+            Self::Kde => "plasmashell",
+            Self::Gnome => "gnome-shell",
+            Self::Cinnamon => "cinnamon",
+            Self::Mate => "mate-session",
+            Self::Budgie => "budgie-panel",
+            Self::Xfce => "xfce4-session",
+            Self::Lxqt => "lxqt-session",
+            Self::Pantheon => "gala",
+            Self::Deepin => "dde-desktop",
+            Self::Cosmic => "cosmic-session",
+        }
+    }
+
+    const ALL: [Desktop; 10] = [
+        Self::Kde,
+        Self::Budgie,
+        Self::Cinnamon,
+        Self::Mate,
+        Self::Xfce,
+        Self::Lxqt,
+        Self::Pantheon,
+        Self::Deepin,
+        Self::Cosmic,
+        // Last: several of the above also run gnome-shell components
+        Self::Gnome,
+    ];
+}
+
+impl fmt::Display for Desktop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Kde => "KDE Plasma",
+            Self::Gnome => "GNOME",
+            Self::Cinnamon => "Cinnamon",
+            Self::Mate => "MATE",
+            Self::Budgie => "Budgie",
+            Self::Xfce => "Xfce",
+            Self::Lxqt => "LXQt",
+            Self::Pantheon => "Pantheon",
+            Self::Deepin => "Deepin",
+            Self::Cosmic => "COSMIC",
+        })
+    }
+}
+
+/// The running desktop and how it was recognised, e.g. `XDG_CURRENT_DESKTOP=Budgie:GNOME`.
+#[derive(Debug, Clone)]
+pub struct Detected {
+    pub desktop: Desktop,
+    pub source: String,
+}
+
+/// Detected once per run; the desktop doesn't change under a running app.
+pub fn detect() -> Option<Detected> {
+    static DETECTED: OnceLock<Option<Detected>> = OnceLock::new();
+    DETECTED
+        .get_or_init(|| from_env().or_else(from_processes))
+        .clone()
+}
+
+/// Whether this is a COSMIC session, e.g. Pop!_OS 24.
+fn is_cosmic() -> bool {
+    env::var("XDG_SESSION_DESKTOP").is_ok_and(|v| v.to_ascii_lowercase().starts_with("cosmic"))
+        || running_processes().iter().any(|p| p == Desktop::Cosmic.process())
+}
+
+fn from_env() -> Option<Detected> {
+    let cosmic = OnceLock::new();
+    let from_name = |name: &str| Desktop::from_name(name, *cosmic.get_or_init(is_cosmic));
+    if let Ok(value) = env::var("XDG_CURRENT_DESKTOP") {
+        // Colon-separated, most specific first (`Budgie:GNOME`, `ubuntu:GNOME`)
+        if let Some(desktop) = value.split(':').find_map(from_name) {
+            return Some(Detected {
+                desktop,
+                source: format!("XDG_CURRENT_DESKTOP={value}"),
+            });
+        }
+    }
+    ["DESKTOP_SESSION", "GDMSESSION"].into_iter().find_map(|var| {
+        let value = env::var(var).ok()?;
+        // Session names can be paths, e.g. /usr/share/xsessions/plasma
+        let name = value.rsplit('/').next().unwrap_or(&value);
+        Some(Detected {
+            desktop: from_name(name)?,
+            source: format!("{var}={value}"),
+        })
+    })
+}
+
+/// Names of the current user's processes, from `/proc/*/comm`. Other users'
+/// are skipped so e.g. the GDM greeter's gnome-shell doesn't count.
+fn running_processes() -> Vec<String> {
+    let (Ok(dir), Ok(me)) = (fs::read_dir("/proc"), fs::metadata("/proc/self")) else {
+        return Vec::new();
+    };
+    dir.filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+        .filter(|entry| entry.metadata().is_ok_and(|m| m.uid() == me.uid()))
+        .filter_map(|entry| fs::read_to_string(entry.path().join("comm")).ok())
+        .map(|comm| comm.trim().to_string())
+        .collect()
+}
+
+fn from_processes() -> Option<Detected> {
+    let processes = running_processes();
+    Desktop::ALL.into_iter().find_map(|desktop| {
+        processes.iter().any(|p| p == desktop.process()).then(|| Detected {
+            desktop,
+            source: format!("{} is running", desktop.process()),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn desktop_names() {
+        for (name, desktop) in [
+            ("KDE", Desktop::Kde),
+            ("plasmawayland", Desktop::Kde),
+            ("GNOME", Desktop::Gnome),
+            ("ubuntu-xorg", Desktop::Gnome),
+            ("pop", Desktop::Gnome),
+            ("X-Cinnamon", Desktop::Cinnamon),
+            ("budgie-desktop", Desktop::Budgie),
+            ("MATE", Desktop::Mate),
+            ("XFCE", Desktop::Xfce),
+            ("LXQt", Desktop::Lxqt),
+            ("Pantheon", Desktop::Pantheon),
+            ("Deepin", Desktop::Deepin),
+            ("DDE", Desktop::Deepin),
+            ("COSMIC", Desktop::Cosmic),
+            (" kde ", Desktop::Kde),
+        ] {
+            assert_eq!(Desktop::from_name(name, false), Some(desktop), "{name}");
+        }
+        for name in ["sway", "Hyprland", "i3", ""] {
+            assert_eq!(Desktop::from_name(name, false), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn pop_session_under_cosmic() {
+        assert_eq!(Desktop::from_name("pop", true), Some(Desktop::Cosmic));
+        assert_eq!(Desktop::from_name("GNOME", true), Some(Desktop::Gnome));
+    }
+}
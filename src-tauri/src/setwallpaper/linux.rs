@@ -0,0 +1,758 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::desktop::{self, Desktop};
+use super::{wlroots, x11, Detection, FitMode, WallpaperSetter};
+use crate::error::AppError;
+use crate::settings::Settings;
+
+pub fn backends(settings: &Settings) -> Vec<Box<dyn WallpaperSetter>> {
+    vec![
+        Box::new(Custom {
+            command: settings.linux_wallpaper_cmd.trim().to_string(),
+        }),
+        Box::new(Kde),
+        Box::new(Gnome),
+        Box::new(Cinnamon),
+        Box::new(Mate),
+        Box::new(Budgie),
+        Box::new(Xfce),
+        Box::new(Lxqt {
+            fit: settings.wallpaper_fit,
+        }),
+        Box::new(Pantheon {
+            fit: settings.wallpaper_fit,
+        }),
+        Box::new(Deepin),
+        Box::new(Cosmic {
+            fit: settings.wallpaper_fit,
+        }),
+        Box::new(wlroots::Hyprpaper),
+        Box::new(wlroots::Swww {
+            options: settings.swww.clone(),
+            fit: settings.wallpaper_fit,
+        }),
+        Box::new(wlroots::Swaybg {
+            fit: settings.wallpaper_fit,
+        }),
+        Box::new(wlroots::Wbg),
+        Box::new(x11::Xwallpaper {
+            fit: settings.wallpaper_fit,
+        }),
+        Box::new(x11::Feh {
+            fit: settings.wallpaper_fit,
+        }),
+        Box::new(x11::Nitrogen {
+            fit: settings.wallpaper_fit,
+        }),
+        Box::new(x11::Hsetroot {
+            fit: settings.wallpaper_fit,
+        }),
+    ]
+}
+
+/// Finds `program` in `PATH`.
+pub fn which(program: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+/// Available when `desktop` is the running desktop and `tool` is installed.
+fn desktop_detection(desktop: Desktop, tool: &str) -> Detection {
+    let Some(detected) = desktop::detect() else {
+        return Detection::unavailable("no desktop environment detected");
+    };
+    if detected.desktop != desktop {
+        return Detection::unavailable(format!("running {} ({})", detected.desktop, detected.source));
+    }
+    match which(tool) {
+        Some(_) => Detection::available(format!("{}, {tool} found", detected.source)),
+        None => Detection::unavailable(format!("{} but {tool} is not on PATH", detected.source)),
+    }
+}
+
+/// The full desktop environment in charge of the wallpaper, if any. Window
+/// manager backends stand aside when one is running, since it draws its own
+/// desktop over the root window.
+pub fn running_desktop() -> Option<Desktop> {
+    desktop::detect().map(|d| d.desktop)
+}
+
+/// Runs `program` with `args`, then `path` when given, without a shell.
+pub fn run(program: &str, args: &[&str], path: Option<&Path>) -> Result<(), AppError> {
+    let mut argv: Vec<&OsStr> = args.iter().map(OsStr::new).collect();
+    argv.extend(path.map(Path::as_os_str));
+    run_os(program, &argv)
+}
+
+/// [`run`] for arguments that aren't UTF-8, such as paths embedded in a flag.
+pub fn run_os(program: &str, args: &[&OsStr]) -> Result<(), AppError> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| AppError::setter_failed(format!("failed to run {program}: {e}")))?;
+    if !output.status.success() {
+        return Err(AppError::setter_failed(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Stdout of a command that exited successfully, trimmed.
+pub fn read_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// `file://` URI for `path`, percent-encoding everything but unreserved
+/// characters and `/`, so quotes, spaces and `#` survive.
+pub fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &b in path.as_os_str().as_encoded_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{b:02X}")),
+        }
+    }
+    uri
+}
+
+/// `'file:///a/b.jpg'` or `'/a/b.jpg'` as printed by `gsettings get` -> `/a/b.jpg`.
+fn gsettings_path(value: &str) -> Option<PathBuf> {
+    let value = value.trim().trim_matches('\'');
+    let path = match value.strip_prefix("file://") {
+        Some(encoded) => percent_decode(encoded),
+        None => value.to_string(),
+    };
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn gsettings_set(schema: &str, key: &str, value: &str) -> Result<(), AppError> {
+    run("gsettings", &["set", schema, key, value], None)
+}
+
+fn gsettings_get(schema: &str, key: &str) -> Option<PathBuf> {
+    gsettings_path(&read_output("gsettings", &["get", schema, key])?)
+}
+
+/// The user's own command from Settings, split with shell-word rules and run
+/// directly with the image path appended.
+struct Custom {
+    command: String,
+}
+
+impl WallpaperSetter for Custom {
+    fn id(&self) -> &'static str {
+        "custom"
+    }
+
+    fn name(&self) -> &'static str {
+        "Custom command"
+    }
+
+    fn detect(&self) -> Detection {
+        if self.command.is_empty() {
+            Detection::unavailable("no wallpaper command set")
+        } else {
+            Detection::available(format!("wallpaper command \"{}\"", self.command))
+        }
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        // Quotes and backslashes work as in a shell, but nothing is expanded
+        let parts = shell_words::split(&self.command)
+            .map_err(|e| AppError::invalid_input(format!("invalid wallpaper command: {e}")))?;
+        let (prog, args) = parts
+            .split_first()
+            .ok_or_else(|| AppError::invalid_input("empty wallpaper command"))?;
+        let mut argv: Vec<&OsStr> = args.iter().map(OsStr::new).collect();
+        argv.push(path.as_os_str());
+        run_os(prog, &argv)
+    }
+}
+
+struct Kde;
+
+impl WallpaperSetter for Kde {
+    fn id(&self) -> &'static str {
+        "kde"
+    }
+
+    fn name(&self) -> &'static str {
+        "KDE Plasma"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Kde, "dbus-send")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        // A JSON string is a valid JavaScript string literal, so this is the
+        // escaping the Plasma script needs
+        let image = serde_json::to_string(&file_uri(path))?;
+        let script = format!(
+            "var allDesktops = desktops(); for (i = 0; i < allDesktops.length; i++) {{ d = allDesktops[i]; d.wallpaperPlugin = 'org.kde.image'; d.currentConfigGroup = Array('Wallpaper', 'org.kde.image', 'General'); d.writeConfig('Image', {image}); }}"
+        );
+        run(
+            "dbus-send",
+            &[
+                "--session",
+                "--dest=org.kde.plasmashell",
+                "--type=method_call",
+                "/PlasmaShell",
+                "org.kde.PlasmaShell.evaluateScript",
+                &format!("string:{script}"),
+            ],
+            None,
+        )
+    }
+}
+
+struct Gnome;
+
+impl WallpaperSetter for Gnome {
+    fn id(&self) -> &'static str {
+        "gnome"
+    }
+
+    fn name(&self) -> &'static str {
+        "GNOME"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Gnome, "gsettings")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        let uri = file_uri(path);
+        gsettings_set("org.gnome.desktop.background", "picture-uri", &uri)?;
+        gsettings_set("org.gnome.desktop.background", "picture-uri-dark", &uri)
+    }
+
+    fn current(&self) -> Option<PathBuf> {
+        gsettings_get("org.gnome.desktop.background", "picture-uri")
+    }
+}
+
+struct Cinnamon;
+
+impl WallpaperSetter for Cinnamon {
+    fn id(&self) -> &'static str {
+        "cinnamon"
+    }
+
+    fn name(&self) -> &'static str {
+        "Cinnamon"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Cinnamon, "gsettings")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        gsettings_set("org.cinnamon.desktop.background", "picture-uri", &file_uri(path))
+    }
+
+    fn current(&self) -> Option<PathBuf> {
+        gsettings_get("org.cinnamon.desktop.background", "picture-uri")
+    }
+}
+
+struct Mate;
+
+impl WallpaperSetter for Mate {
+    fn id(&self) -> &'static str {
+        "mate"
+    }
+
+    fn name(&self) -> &'static str {
+        "MATE"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Mate, "gsettings")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        gsettings_set("org.mate.background", "picture-filename", &path.to_string_lossy())
+    }
+
+    fn current(&self) -> Option<PathBuf> {
+        gsettings_get("org.mate.background", "picture-filename")
+    }
+}
+
+struct Budgie;
+
+impl WallpaperSetter for Budgie {
+    fn id(&self) -> &'static str {
+        "budgie"
+    }
+
+    fn name(&self) -> &'static str {
+        "Budgie"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Budgie, "gsettings")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        gsettings_set("org.gnome.desktop.background", "picture-uri", &file_uri(path))
+    }
+
+    fn current(&self) -> Option<PathBuf> {
+        gsettings_get("org.gnome.desktop.background", "picture-uri")
+    }
+}
+
+/// Sets every `last-image` property, one per monitor and workspace.
+struct Xfce;
+
+impl Xfce {
+    /// `/backdrop/screen0/monitor{name}/workspace0/last-image` properties,
+    /// limited to `monitor` when given.
+    fn image_properties(monitor: Option<&str>) -> Result<Vec<String>, AppError> {
+        let list = read_output("xfconf-query", &["-c", "xfce4-desktop", "-l"])
+            .ok_or_else(|| AppError::setter_failed("xfconf-query couldn't list desktop properties"))?;
+        let segment = monitor.map(|m| format!("/monitor{m}/"));
+        Ok(list
+            .lines()
+            .filter(|prop| prop.ends_with("/last-image"))
+            .filter(|prop| segment.as_ref().is_none_or(|s| prop.contains(s.as_str())))
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+impl WallpaperSetter for Xfce {
+    fn id(&self) -> &'static str {
+        "xfce"
+    }
+
+    fn name(&self) -> &'static str {
+        "Xfce"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Xfce, "xfconf-query")
+    }
+
+    fn apply(&self, path: &Path, monitor: Option<&str>) -> Result<(), AppError> {
+        let props = Self::image_properties(monitor)?;
+        if props.is_empty() {
+            return Err(AppError::setter_failed(match monitor {
+                Some(m) => format!("no Xfce backdrop found for monitor {m}"),
+                None => "no Xfce backdrop properties found".to_string(),
+            }));
+        }
+        for prop in props {
+            run("xfconf-query", &["-c", "xfce4-desktop", "-p", &prop, "-s"], Some(path))?;
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> Option<PathBuf> {
+        let prop = Self::image_properties(None).ok()?.into_iter().next()?;
+        read_output("xfconf-query", &["-c", "xfce4-desktop", "-p", &prop])
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    }
+
+    fn supports_per_monitor(&self) -> bool {
+        true
+    }
+}
+
+/// pcmanfm-qt, which draws the LXQt desktop.
+struct Lxqt {
+    fit: FitMode,
+}
+
+impl WallpaperSetter for Lxqt {
+    fn id(&self) -> &'static str {
+        "lxqt"
+    }
+
+    fn name(&self) -> &'static str {
+        "LXQt"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Lxqt, "pcmanfm-qt")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        let mode = match self.fit {
+            FitMode::Fill => "zoom",
+            FitMode::Fit => "fit",
+            FitMode::Stretch => "stretch",
+            FitMode::Center => "center",
+            FitMode::Tile => "tile",
+        };
+        let mut wallpaper = OsString::from("--set-wallpaper=");
+        wallpaper.push(path);
+        let mode = format!("--wallpaper-mode={mode}");
+        run_os("pcmanfm-qt", &[&wallpaper, OsStr::new(&mode)])
+    }
+
+    /// `Wallpaper=` in pcmanfm-qt's LXQt profile.
+    fn current(&self) -> Option<PathBuf> {
+        let config = env::var_os("HOME").map(|home| {
+            PathBuf::from(home).join(".config/pcmanfm-qt/lxqt/settings.conf")
+        })?;
+        fs::read_to_string(config)
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("Wallpaper="))
+            .map(PathBuf::from)
+    }
+}
+
+/// elementary OS. Gala reads the GNOME background keys.
+struct Pantheon {
+    fit: FitMode,
+}
+
+impl WallpaperSetter for Pantheon {
+    fn id(&self) -> &'static str {
+        "pantheon"
+    }
+
+    fn name(&self) -> &'static str {
+        "Pantheon"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Pantheon, "gsettings")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        let options = match self.fit {
+            FitMode::Fill => "zoom",
+            FitMode::Fit => "scaled",
+            FitMode::Stretch => "stretched",
+            FitMode::Center => "centered",
+            FitMode::Tile => "wallpaper",
+        };
+        gsettings_set("org.gnome.desktop.background", "picture-options", options)?;
+        gsettings_set("org.gnome.desktop.background", "picture-uri", &file_uri(path))
+    }
+
+    fn current(&self) -> Option<PathBuf> {
+        gsettings_get("org.gnome.desktop.background", "picture-uri")
+    }
+}
+
+/// Deepin's appearance daemon, which sets each monitor separately. V23
+/// renamed the D-Bus service, so both names are tried.
+struct Deepin;
+
+impl Deepin {
+    const SERVICES: [(&'static str, &'static str, &'static str); 2] = [
+        (
+            "org.deepin.dde.Appearance1",
+            "/org/deepin/dde/Appearance1",
+            "org.deepin.dde.Appearance1.SetMonitorBackground",
+        ),
+        (
+            "com.deepin.daemon.Appearance",
+            "/com/deepin/daemon/Appearance",
+            "com.deepin.daemon.Appearance.SetMonitorBackground",
+        ),
+    ];
+
+    fn set_monitor(monitor: &str, uri: &str) -> Result<(), AppError> {
+        let mut last_error = None;
+        for (dest, object, method) in Self::SERVICES {
+            let result = run(
+                "dbus-send",
+                &[
+                    "--session",
+                    "--print-reply",
+                    &format!("--dest={dest}"),
+                    object,
+                    method,
+                    &format!("string:{monitor}"),
+                    &format!("string:{uri}"),
+                ],
+                None,
+            );
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| AppError::setter_failed("no Deepin appearance service")))
+    }
+}
+
+impl WallpaperSetter for Deepin {
+    fn id(&self) -> &'static str {
+        "deepin"
+    }
+
+    fn name(&self) -> &'static str {
+        "Deepin"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Deepin, "dbus-send")
+    }
+
+    fn apply(&self, path: &Path, monitor: Option<&str>) -> Result<(), AppError> {
+        let uri = file_uri(path);
+        let monitors = match monitor {
+            Some(monitor) => vec![monitor.to_string()],
+            None => x11::monitor_names(),
+        };
+        if monitors.is_empty() {
+            return Err(AppError::setter_failed("couldn't list monitors (is xrandr installed?)"));
+        }
+        for monitor in monitors {
+            Self::set_monitor(&monitor, &uri)?;
+        }
+        Ok(())
+    }
+
+    fn supports_per_monitor(&self) -> bool {
+        true
+    }
+}
+
+/// cosmic-bg, which watches its config directory and applies changes itself.
+/// cosmic-bg's own fallback, used when the config file doesn't exist yet.
+const COSMIC_DEFAULT_ENTRY: &str = "(
+    output: \"all\",
+    source: Path(\"\"),
+    filter_by_theme: true,
+    rotation_frequency: 300,
+    filter_method: Lanczos,
+    scaling_mode: Zoom,
+    sampling_method: Alphanumerical,
+)";
+
+struct Cosmic {
+    fit: FitMode,
+}
+
+impl Cosmic {
+    fn config_file() -> Option<PathBuf> {
+        let config = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("cosmic/com.system76.CosmicBackground/v1/all"))
+    }
+
+    /// Quotes `s` as a RON string.
+    fn ron_string(s: &str) -> String {
+        let mut out = String::with_capacity(s.len() + 2);
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    }
+
+    /// Byte range of the value of top-level field `name` in a RON struct like
+    /// `(output: "all", source: Path("…"), …)`, skipping strings and nested brackets.
+    fn ron_field(entry: &str, name: &str) -> Option<Range<usize>> {
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut field_start = None;
+        let mut value_start = None;
+        for (i, b) in entry.bytes().enumerate() {
+            if in_string {
+                match b {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match b {
+                b'"' => in_string = true,
+                b'(' | b'[' | b'{' => {
+                    depth += 1;
+                    if depth == 1 {
+                        field_start = Some(i + 1);
+                    }
+                }
+                b':' if depth == 1 && value_start.is_none() => {
+                    if let Some(start) = field_start.take() {
+                        if entry[start..i].trim() == name {
+                            value_start = Some(i + 1);
+                        }
+                    }
+                }
+                b',' | b')' | b']' | b'}' => {
+                    if depth == 1 {
+                        if let Some(start) = value_start {
+                            let value = &entry[start..i];
+                            let start = start + value.len() - value.trim_start().len();
+                            return Some(start..start + value.trim().len());
+                        }
+                        field_start = Some(i + 1);
+                    }
+                    if b != b',' {
+                        depth -= 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// `entry` with the value of `name` replaced by `value`.
+    fn replace_field(entry: &str, name: &str, value: &str) -> Option<String> {
+        let range = Self::ron_field(entry, name)?;
+        Some(format!("{}{value}{}", &entry[..range.start], &entry[range.end..]))
+    }
+}
+
+impl WallpaperSetter for Cosmic {
+    fn id(&self) -> &'static str {
+        "cosmic"
+    }
+
+    fn name(&self) -> &'static str {
+        "COSMIC"
+    }
+
+    fn detect(&self) -> Detection {
+        desktop_detection(Desktop::Cosmic, "cosmic-bg")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        let file = Self::config_file()
+            .ok_or_else(|| AppError::setter_failed("couldn't find the COSMIC config directory"))?;
+        let scaling = match self.fit {
+            FitMode::Fill | FitMode::Center | FitMode::Tile => "Zoom",
+            FitMode::Fit => "Fit((0.0, 0.0, 0.0))",
+            FitMode::Stretch => "Stretch",
+        };
+        // Only the image and scaling are ours; rotation, filters and the rest
+        // stay as the user set them in COSMIC Settings
+        let entry = match fs::read_to_string(&file) {
+            Ok(entry) => entry,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => COSMIC_DEFAULT_ENTRY.to_string(),
+            Err(e) => return Err(e.into()),
+        };
+        let source = format!("Path({})", Self::ron_string(&path.to_string_lossy()));
+        let entry = Self::replace_field(&entry, "source", &source)
+            .and_then(|entry| Self::replace_field(&entry, "scaling_mode", scaling))
+            .ok_or_else(|| {
+                AppError::setter_failed(format!("couldn't parse {}, leaving it untouched", file.display()))
+            })?;
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&file, entry)?;
+        Ok(())
+    }
+
+    /// The `Path("…")` source of the `all` entry.
+    fn current(&self) -> Option<PathBuf> {
+        let entry = fs::read_to_string(Self::config_file()?).ok()?;
+        let source = &entry[Self::ron_field(&entry, "source")?];
+        let quoted = source.strip_prefix("Path(")?.strip_suffix(')')?.trim();
+        let path = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        Some(PathBuf::from(path.replace("\\\"", "\"").replace("\\\\", "\\")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_uri_escapes_reserved_characters() {
+        assert_eq!(file_uri(Path::new("/home/me/a.jpg")), "file:///home/me/a.jpg");
+        assert_eq!(
+            file_uri(Path::new("/tmp/it's a #1 100%.jpg")),
+            "file:///tmp/it%27s%20a%20%231%20100%25.jpg"
+        );
+        assert_eq!(file_uri(Path::new("/tmp/café.png")), "file:///tmp/caf%C3%A9.png");
+    }
+
+    #[test]
+    fn percent_decode_reverses_file_uri() {
+        let path = "/tmp/it's a \"#1\" 100% café.jpg";
+        let uri = file_uri(Path::new(path));
+        assert_eq!(percent_decode(uri.strip_prefix("file://").unwrap()), path);
+        // Stray or truncated escapes are kept as they are
+        assert_eq!(percent_decode("/a%zz/b%4"), "/a%zz/b%4");
+        assert_eq!(gsettings_path("'file:///a%20b.jpg'"), Some(PathBuf::from("/a b.jpg")));
+        assert_eq!(gsettings_path("'/a b.jpg'"), Some(PathBuf::from("/a b.jpg")));
+    }
+
+    const COSMIC_ENTRY: &str = r#"(
+    output: "all",
+    source: Path("/home/me/a \"b\", c.jpg"),
+    filter_by_theme: true,
+    rotation_frequency: 600,
+    filter_method: Lanczos,
+    scaling_mode: Fit((0.0, 0.0, 0.0)),
+    sampling_method: Alphanumerical,
+)"#;
+
+    #[test]
+    fn cosmic_field_skips_strings_and_nesting() {
+        let range = Cosmic::ron_field(COSMIC_ENTRY, "scaling_mode").unwrap();
+        assert_eq!(&COSMIC_ENTRY[range], "Fit((0.0, 0.0, 0.0))");
+        let range = Cosmic::ron_field(COSMIC_ENTRY, "source").unwrap();
+        assert_eq!(&COSMIC_ENTRY[range], r#"Path("/home/me/a \"b\", c.jpg")"#);
+        assert!(Cosmic::ron_field(COSMIC_ENTRY, "missing").is_none());
+        assert!(Cosmic::ron_field("not ron", "source").is_none());
+    }
+
+    #[test]
+    fn cosmic_replace_keeps_other_fields() {
+        let entry = Cosmic::replace_field(COSMIC_ENTRY, "scaling_mode", "Zoom").unwrap();
+        assert!(entry.contains("scaling_mode: Zoom,"));
+        assert!(entry.contains("rotation_frequency: 600,"));
+        assert!(entry.contains("sampling_method: Alphanumerical,"));
+    }
+}
@@ -0,0 +1,101 @@
+use objc::runtime::Object;
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+
+use super::{Detection, WallpaperSetter};
+use crate::error::AppError;
+use crate::settings::Settings;
+
+pub fn backends(_settings: &Settings) -> Vec<Box<dyn WallpaperSetter>> {
+    vec![Box::new(Workspace)]
+}
+
+/// `NSWorkspace.setDesktopImageURL`, applied to every screen.
+struct Workspace;
+
+impl WallpaperSetter for Workspace {
+    fn id(&self) -> &'static str {
+        "macos"
+    }
+
+    fn name(&self) -> &'static str {
+        "macOS"
+    }
+
+    fn detect(&self) -> Detection {
+        Detection::available("NSWorkspace is always available")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        // Count attached screens first (fast, no alloc needed)
+        let count: usize = unsafe {
+            let screens: *mut Object = msg_send![class!(NSScreen), screens];
+            msg_send![screens, count]
+        };
+
+        if count == 0 {
+            return Err(AppError::setter_failed("no screens found"));
+        }
+
+        // macOS serialises setDesktopImageURL calls internally (one per screen,
+        // ~5 s each). Spawn a detached thread per screen and return immediately
+        // so the UI isn't blocked — the wallpaper applies in the background.
+        // The file is already verified to exist before this function is called.
+        for i in 0..count {
+            let path = path.to_string_lossy().into_owned();
+            std::thread::spawn(move || {
+                let Ok(path_c) = CString::new(path.as_str()) else { return };
+                unsafe {
+                    let alloc: *mut Object = msg_send![class!(NSString), alloc];
+                    let path_ns: *mut Object =
+                        msg_send![alloc, initWithUTF8String: path_c.as_ptr()];
+                    let url: *mut Object =
+                        msg_send![class!(NSURL), fileURLWithPath: path_ns];
+                    let options: *mut Object =
+                        msg_send![class!(NSDictionary), dictionary];
+                    let workspace: *mut Object =
+                        msg_send![class!(NSWorkspace), sharedWorkspace];
+                    let screens: *mut Object =
+                        msg_send![class!(NSScreen), screens];
+                    let screen: *mut Object =
+                        msg_send![screens, objectAtIndex: i];
+
+                    let _ok: bool = msg_send![
+                        workspace,
+                        setDesktopImageURL: url
+                        forScreen: screen
+                        options: options
+                        error: std::ptr::null_mut::<*mut Object>()
+                    ];
+
+                    let _: () = msg_send![path_ns, release];
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    fn current(&self) -> Option<PathBuf> {
+        unsafe {
+            let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let screen: *mut Object = msg_send![class!(NSScreen), mainScreen];
+            if screen.is_null() {
+                return None;
+            }
+            let url: *mut Object = msg_send![workspace, desktopImageURLForScreen: screen];
+            if url.is_null() {
+                return None;
+            }
+            let path_ns: *mut Object = msg_send![url, path];
+            if path_ns.is_null() {
+                return None;
+            }
+            let utf8: *const std::os::raw::c_char = msg_send![path_ns, UTF8String];
+            if utf8.is_null() {
+                return None;
+            }
+            Some(PathBuf::from(CStr::from_ptr(utf8).to_string_lossy().into_owned()))
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+use super::{Detection, WallpaperSetter};
+use crate::error::AppError;
+use crate::settings::Settings;
+
+#[link(name = "user32")]
+extern "system" {
+    fn SystemParametersInfoW(
+        uiAction: u32,
+        uiParam: u32,
+        pvParam: *mut u16,
+        fWinIni: u32,
+    ) -> i32;
+}
+
+const SPI_GETDESKWALLPAPER: u32 = 0x0073;
+const SPI_SETDESKWALLPAPER: u32 = 0x0014;
+const SPIF_UPDATEINIFILE: u32 = 0x01;
+const SPIF_SENDCHANGE: u32 = 0x02;
+const MAX_PATH: usize = 260;
+
+pub fn backends(_settings: &Settings) -> Vec<Box<dyn WallpaperSetter>> {
+    vec![Box::new(SystemParameters)]
+}
+
+/// `SystemParametersInfoW(SPI_SETDESKWALLPAPER)`, which covers every monitor.
+struct SystemParameters;
+
+impl WallpaperSetter for SystemParameters {
+    fn id(&self) -> &'static str {
+        "windows"
+    }
+
+    fn name(&self) -> &'static str {
+        "Windows"
+    }
+
+    fn detect(&self) -> Detection {
+        Detection::available("SystemParametersInfoW is always available")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        let wide: Vec<u16> = OsStr::new(path)
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+
+        let result = unsafe {
+            SystemParametersInfoW(
+                SPI_SETDESKWALLPAPER,
+                0,
+                wide.as_ptr().cast_mut(),
+                SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+            )
+        };
+
+        if result == 0 {
+            Err(AppError::setter_failed("SystemParametersInfoW failed"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn current(&self) -> Option<PathBuf> {
+        let mut buf = [0u16; MAX_PATH];
+        let result = unsafe {
+            SystemParametersInfoW(SPI_GETDESKWALLPAPER, MAX_PATH as u32, buf.as_mut_ptr(), 0)
+        };
+        if result == 0 {
+            return None;
+        }
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+        (len > 0).then(|| PathBuf::from(OsString::from_wide(&buf[..len])))
+    }
+}
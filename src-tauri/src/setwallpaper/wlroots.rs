@@ -0,0 +1,473 @@
+// Backends for wlroots compositors (Sway, river, Hyprland, …), which have no
+// wallpaper setting of their own and rely on layer-shell clients instead.
+
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::linux::{read_output, run, running_desktop, which};
+use super::{Detection, FitMode, SwwwOptions, WallpaperSetter};
+use crate::error::AppError;
+
+/// How long a freshly spawned background client gets to draw before the one
+/// it replaces is killed, so the desktop never flashes empty.
+const HANDOVER_DELAY: Duration = Duration::from_millis(400);
+
+/// Output name meaning "every output", both to swaybg and in the client pid table.
+const ALL_OUTPUTS: &str = "*";
+
+fn wayland_detection(tool: &str) -> Detection {
+    if env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Detection::unavailable("not a Wayland session");
+    }
+    if let Some(desktop) = running_desktop() {
+        return Detection::unavailable(format!("{desktop} manages the wallpaper"));
+    }
+    match which(tool) {
+        Some(_) => Detection::available(format!("Wayland session, {tool} found")),
+        None => Detection::unavailable(format!("{tool} is not on PATH")),
+    }
+}
+
+/// Long-running wallpaper clients (swaybg, wbg) that keep the image on screen
+/// for as long as they live. One client per program covers every output; pids
+/// are kept in `$XDG_RUNTIME_DIR` so clients started by a previous run are
+/// cleaned up too.
+struct Clients {
+    program: &'static str,
+}
+
+static CLIENT_LOCK: Mutex<()> = Mutex::new(());
+
+impl Clients {
+    fn pid_file(&self) -> PathBuf {
+        let dir = env::var_os("XDG_RUNTIME_DIR").map_or_else(env::temp_dir, PathBuf::from);
+        dir.join(format!("wallchemybar-{}.pids", self.program))
+    }
+
+    /// `output -> pid`, one `output\tpid` pair per line.
+    fn load(&self) -> HashMap<String, u32> {
+        fs::read_to_string(self.pid_file())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (output, pid) = line.split_once('\t')?;
+                Some((output.to_string(), pid.parse().ok()?))
+            })
+            .collect()
+    }
+
+    fn save(&self, pids: &HashMap<String, u32>) {
+        let contents: String = pids
+            .iter()
+            .map(|(output, pid)| format!("{output}\t{pid}\n"))
+            .collect();
+        if let Err(e) = fs::write(self.pid_file(), contents) {
+            warn!("{}: couldn't save pids: {e}", self.program);
+        }
+    }
+
+    /// Whether `pid` is still one of our clients; pids get reused.
+    fn is_ours(&self, pid: u32) -> bool {
+        let comm = fs::read_to_string(format!("/proc/{pid}/comm")).unwrap_or_default();
+        comm.trim() == self.program
+    }
+
+    fn kill(&self, pid: u32) {
+        if !self.is_ours(pid) {
+            return;
+        }
+        debug!("{}: stopping pid {}", self.program, pid);
+        if let Err(e) = Command::new("kill").arg(pid.to_string()).status() {
+            warn!("{}: couldn't stop pid {}: {e}", self.program, pid);
+        }
+    }
+
+    /// Arguments the running client was started with, from `/proc/{pid}/cmdline`.
+    fn running_args(&self) -> Option<Vec<OsString>> {
+        let pid = *self.load().get(ALL_OUTPUTS)?;
+        if !self.is_ours(pid) {
+            return None;
+        }
+        let cmdline = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+        let args = cmdline
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .skip(1)
+            .map(|arg| OsStr::from_bytes(arg).to_os_string())
+            .collect();
+        Some(args)
+    }
+
+    /// Starts the client with `args` and stops every client it replaces,
+    /// including any left from per-output clients of earlier versions.
+    /// Blocks for [`HANDOVER_DELAY`].
+    fn replace<S: AsRef<OsStr>>(&self, args: &[S]) -> Result<(), AppError> {
+        let child = Command::new(self.program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| AppError::setter_failed(format!("failed to start {}: {e}", self.program)))?;
+        let pid = child.id();
+        // Reap it in the background so it doesn't linger as a zombie once killed
+        std::thread::spawn(move || {
+            let mut child = child;
+            let _ = child.wait();
+        });
+
+        std::thread::sleep(HANDOVER_DELAY);
+        if fs::metadata(format!("/proc/{pid}")).is_err() {
+            return Err(AppError::setter_failed(format!("{} exited immediately", self.program)));
+        }
+
+        let _lock = CLIENT_LOCK.lock().unwrap();
+        for (_, old) in self.load() {
+            self.kill(old);
+        }
+        self.save(&HashMap::from([(ALL_OUTPUTS.to_string(), pid)]));
+        info!("{}: started pid {}", self.program, pid);
+        Ok(())
+    }
+}
+
+/// swaybg arguments with `output`'s section (`-o output -i … -m …`) set to
+/// `section`, keeping the other outputs' sections from `current` as long as
+/// their image still exists. swaybg prefers a named output's section over
+/// `*`, so setting one monitor leaves the rest showing the `*` image; setting
+/// `*` drops every per-output section.
+fn merge_outputs(current: &[OsString], output: &str, section: &[OsString]) -> Vec<OsString> {
+    let mut sections: Vec<(OsString, Vec<OsString>)> = Vec::new();
+    if output != ALL_OUTPUTS {
+        let mut args = current.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-o" {
+                sections.push((args.next().cloned().unwrap_or_default(), Vec::new()));
+                continue;
+            }
+            if sections.is_empty() {
+                sections.push((ALL_OUTPUTS.into(), Vec::new()));
+            }
+            if let Some((_, options)) = sections.last_mut() {
+                options.push(arg.clone());
+            }
+        }
+    }
+    sections.retain(|(name, options)| {
+        let image_exists = options
+            .windows(2)
+            .find(|pair| pair[0] == "-i")
+            .is_some_and(|pair| Path::new(&pair[1]).exists());
+        name != output && image_exists
+    });
+    sections.push((output.into(), section.to_vec()));
+
+    sections
+        .into_iter()
+        .flat_map(|(name, options)| [OsString::from("-o"), name].into_iter().chain(options))
+        .collect()
+}
+
+/// swaybg, restarted for every change. A monitor gets its own section in the
+/// one running client.
+pub struct Swaybg {
+    pub fit: FitMode,
+}
+
+impl WallpaperSetter for Swaybg {
+    fn id(&self) -> &'static str {
+        "swaybg"
+    }
+
+    fn name(&self) -> &'static str {
+        "swaybg"
+    }
+
+    fn detect(&self) -> Detection {
+        wayland_detection("swaybg")
+    }
+
+    fn apply(&self, path: &Path, monitor: Option<&str>) -> Result<(), AppError> {
+        let mode = match self.fit {
+            FitMode::Fill => "fill",
+            FitMode::Fit => "fit",
+            FitMode::Stretch => "stretch",
+            FitMode::Center => "center",
+            FitMode::Tile => "tile",
+        };
+        let section = ["-i".into(), path.into(), "-m".into(), mode.into()];
+        let clients = Clients { program: "swaybg" };
+        let current = clients.running_args().unwrap_or_default();
+        clients.replace(&merge_outputs(&current, monitor.unwrap_or(ALL_OUTPUTS), &section))
+    }
+
+    fn supports_per_monitor(&self) -> bool {
+        true
+    }
+}
+
+/// wbg, which like swaybg has to stay running. It always covers every output.
+pub struct Wbg;
+
+impl WallpaperSetter for Wbg {
+    fn id(&self) -> &'static str {
+        "wbg"
+    }
+
+    fn name(&self) -> &'static str {
+        "wbg"
+    }
+
+    fn detect(&self) -> Detection {
+        wayland_detection("wbg")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        Clients { program: "wbg" }.replace(&[path])
+    }
+}
+
+/// swww, talking to `swww-daemon` (started on demand).
+pub struct Swww {
+    pub options: SwwwOptions,
+    pub fit: FitMode,
+}
+
+impl Swww {
+    fn daemon_running() -> bool {
+        read_output("swww", &["query"]).is_some()
+    }
+
+    fn start_daemon() -> Result<(), AppError> {
+        let daemon = if which("swww-daemon").is_some() { "swww-daemon" } else { "swww" };
+        let mut cmd = Command::new(daemon);
+        if daemon == "swww" {
+            cmd.arg("init");
+        }
+        info!("swww: starting {}", daemon);
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| AppError::setter_failed(format!("failed to start {daemon}: {e}")))?;
+        // The daemon needs a moment before it accepts requests
+        for _ in 0..20 {
+            std::thread::sleep(Duration::from_millis(100));
+            if Self::daemon_running() {
+                return Ok(());
+            }
+        }
+        Err(AppError::setter_failed("swww-daemon didn't start"))
+    }
+}
+
+impl WallpaperSetter for Swww {
+    fn id(&self) -> &'static str {
+        "swww"
+    }
+
+    fn name(&self) -> &'static str {
+        "swww"
+    }
+
+    fn detect(&self) -> Detection {
+        wayland_detection("swww")
+    }
+
+    fn apply(&self, path: &Path, monitor: Option<&str>) -> Result<(), AppError> {
+        if !Self::daemon_running() {
+            Self::start_daemon()?;
+        }
+        let options = &self.options;
+        let duration = options.transition_duration.to_string();
+        let fps = options.transition_fps.to_string();
+        let angle = options.transition_angle.to_string();
+        let resize = match self.fit {
+            FitMode::Fill => "crop",
+            FitMode::Fit => "fit",
+            FitMode::Stretch => "stretch",
+            FitMode::Center | FitMode::Tile => "no",
+        };
+        let mut args = vec![
+            "img",
+            "--resize",
+            resize,
+            "--transition-type",
+            options.transition_type.as_str(),
+            "--transition-duration",
+            duration.as_str(),
+            "--transition-fps",
+            fps.as_str(),
+            "--transition-angle",
+            angle.as_str(),
+        ];
+        if !options.transition_pos.is_empty() {
+            args.extend(["--transition-pos", options.transition_pos.as_str()]);
+        }
+        if let Some(monitor) = monitor {
+            args.extend(["--outputs", monitor]);
+        }
+        run("swww", &args, Some(path))
+    }
+
+    /// First output's image from `swww query`, whose lines look like
+    /// `DP-1: 2560x1440, scale: 1, currently displaying: image: /path/to.jpg`.
+    fn current(&self) -> Option<PathBuf> {
+        read_output("swww", &["query"])?
+            .lines()
+            .find_map(|line| line.split_once("image: ").map(|(_, path)| PathBuf::from(path.trim())))
+    }
+
+    fn supports_per_monitor(&self) -> bool {
+        true
+    }
+}
+
+/// Path last handed to hyprpaper, unloaded after the next change when
+/// `unload unused` isn't supported.
+static HYPRPAPER_LOADED: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// hyprpaper, driven over its IPC socket: the new image is preloaded, shown,
+/// and the previous one unloaded so preloads don't pile up in memory.
+pub struct Hyprpaper;
+
+impl Hyprpaper {
+    fn socket() -> Option<PathBuf> {
+        let signature = env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+        let runtime = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+        runtime
+            .map(|dir| dir.join("hypr"))
+            .into_iter()
+            .chain([PathBuf::from("/tmp/hypr")])
+            .map(|dir| dir.join(&signature).join(".hyprpaper.sock"))
+            .find(|path| path.exists())
+    }
+
+    /// Sends one request and returns hyprpaper's reply.
+    fn request(command: &str) -> Result<String, AppError> {
+        let socket = Self::socket().ok_or_else(|| AppError::setter_failed("hyprpaper isn't running"))?;
+        let mut stream = UnixStream::connect(&socket)
+            .map_err(|e| AppError::setter_failed(format!("couldn't connect to hyprpaper: {e}")))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5))).ok();
+        stream
+            .write_all(command.as_bytes())
+            .map_err(|e| AppError::setter_failed(format!("hyprpaper request failed: {e}")))?;
+        let mut reply = String::new();
+        stream
+            .read_to_string(&mut reply)
+            .map_err(|e| AppError::setter_failed(format!("hyprpaper didn't reply: {e}")))?;
+        debug!("hyprpaper: {} -> {}", command, reply.trim());
+        Ok(reply.trim().to_string())
+    }
+
+    fn expect_ok(command: &str) -> Result<(), AppError> {
+        match Self::request(command)? {
+            reply if reply == "ok" => Ok(()),
+            reply => Err(AppError::setter_failed(format!("hyprpaper: {reply}"))),
+        }
+    }
+}
+
+impl WallpaperSetter for Hyprpaper {
+    fn id(&self) -> &'static str {
+        "hyprpaper"
+    }
+
+    fn name(&self) -> &'static str {
+        "hyprpaper"
+    }
+
+    fn detect(&self) -> Detection {
+        if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_none() {
+            return Detection::unavailable("not running under Hyprland");
+        }
+        match Self::socket() {
+            Some(socket) => Detection::available(format!("Hyprland, hyprpaper socket at {}", socket.display())),
+            None => Detection::unavailable("Hyprland, but hyprpaper isn't running"),
+        }
+    }
+
+    fn apply(&self, path: &Path, monitor: Option<&str>) -> Result<(), AppError> {
+        let path_str = path.to_string_lossy();
+        Self::expect_ok(&format!("preload {path_str}"))?;
+        Self::expect_ok(&format!("wallpaper {},{path_str}", monitor.unwrap_or_default()))?;
+
+        let previous = HYPRPAPER_LOADED.lock().unwrap().replace(path.to_path_buf());
+        if Self::expect_ok("unload unused").is_err() {
+            if let Some(previous) = previous.filter(|p| p != path) {
+                if let Err(e) = Self::expect_ok(&format!("unload {}", previous.display())) {
+                    warn!("hyprpaper: couldn't unload {:?}: {e}", previous);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// First entry of `listactive`, whose lines look like `DP-1 = /path/to.jpg`.
+    fn current(&self) -> Option<PathBuf> {
+        Self::request("listactive")
+            .ok()?
+            .lines()
+            .find_map(|line| line.split_once(" = ").map(|(_, path)| PathBuf::from(path.trim())))
+    }
+
+    fn supports_per_monitor(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn merge_outputs_keeps_all_outputs_under_a_monitor() {
+        let image = env!("CARGO_MANIFEST_DIR");
+        let current = args(&["-o", "*", "-i", image, "-m", "fill"]);
+        let merged = merge_outputs(&current, "DP-1", &args(&["-i", "/new.jpg", "-m", "fit"]));
+        assert_eq!(
+            merged,
+            args(&["-o", "*", "-i", image, "-m", "fill", "-o", "DP-1", "-i", "/new.jpg", "-m", "fit"])
+        );
+    }
+
+    #[test]
+    fn merge_outputs_replaces_the_same_monitor() {
+        let image = env!("CARGO_MANIFEST_DIR");
+        let current = args(&["-o", "*", "-i", image, "-m", "fill", "-o", "DP-1", "-i", image, "-m", "fit"]);
+        let merged = merge_outputs(&current, "DP-1", &args(&["-i", "/new.jpg", "-m", "fit"]));
+        assert_eq!(
+            merged,
+            args(&["-o", "*", "-i", image, "-m", "fill", "-o", "DP-1", "-i", "/new.jpg", "-m", "fit"])
+        );
+    }
+
+    #[test]
+    fn merge_outputs_all_outputs_drops_monitors() {
+        let image = env!("CARGO_MANIFEST_DIR");
+        let current = args(&["-o", "DP-1", "-i", image, "-m", "fit"]);
+        let merged = merge_outputs(&current, "*", &args(&["-i", "/new.jpg", "-m", "fill"]));
+        assert_eq!(merged, args(&["-o", "*", "-i", "/new.jpg", "-m", "fill"]));
+    }
+
+    #[test]
+    fn merge_outputs_drops_missing_images() {
+        let current = args(&["-o", "*", "-i", "/no/such/image.jpg", "-m", "fill"]);
+        let merged = merge_outputs(&current, "DP-1", &args(&["-i", "/new.jpg", "-m", "fit"]));
+        assert_eq!(merged, args(&["-o", "DP-1", "-i", "/new.jpg", "-m", "fit"]));
+    }
+}
@@ -0,0 +1,208 @@
+// Backends for bare X11 window managers (i3, bspwm, awesome, Openbox, …),
+// which leave the root window to a standalone tool.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::linux::{read_output, run, running_desktop, which};
+use super::{Detection, FitMode, WallpaperSetter};
+use crate::error::AppError;
+
+/// Available in an X11 session with no full desktop environment running,
+/// when `tool` is installed.
+fn x11_detection(tool: &str) -> Detection {
+    if env::var_os("DISPLAY").is_none() {
+        return Detection::unavailable("no X11 display");
+    }
+    if env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland") {
+        return Detection::unavailable("Wayland session");
+    }
+    if let Some(desktop) = running_desktop() {
+        return Detection::unavailable(format!("{desktop} manages the wallpaper"));
+    }
+    match which(tool) {
+        Some(_) => Detection::available(format!("X11 window manager, {tool} found")),
+        None => Detection::unavailable(format!("{tool} is not on PATH")),
+    }
+}
+
+/// Xinerama index of an output. `xrandr --listactivemonitors` lines look
+/// like ` 0: +*DP-1 2560/597x1440/336+0+0  DP-1`.
+fn monitor_index(name: &str) -> Option<usize> {
+    monitor_names().iter().position(|n| n == name)
+}
+
+/// Names of the active outputs, in xinerama order.
+pub fn monitor_names() -> Vec<String> {
+    read_output("xrandr", &["--listactivemonitors"])
+        .unwrap_or_default()
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().last().map(str::to_string))
+        .collect()
+}
+
+fn home_file(relative: &str) -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(relative))
+}
+
+pub struct Xwallpaper {
+    pub fit: FitMode,
+}
+
+impl WallpaperSetter for Xwallpaper {
+    fn id(&self) -> &'static str {
+        "xwallpaper"
+    }
+
+    fn name(&self) -> &'static str {
+        "xwallpaper"
+    }
+
+    fn detect(&self) -> Detection {
+        x11_detection("xwallpaper")
+    }
+
+    fn apply(&self, path: &Path, monitor: Option<&str>) -> Result<(), AppError> {
+        let mode = match self.fit {
+            FitMode::Fill => "--zoom",
+            FitMode::Fit => "--maximize",
+            FitMode::Stretch => "--stretch",
+            FitMode::Center => "--center",
+            FitMode::Tile => "--tile",
+        };
+        let mut args = Vec::new();
+        if let Some(monitor) = monitor {
+            args.extend(["--output", monitor]);
+        }
+        args.push(mode);
+        run("xwallpaper", &args, Some(path))
+    }
+
+    fn supports_per_monitor(&self) -> bool {
+        true
+    }
+}
+
+/// feh, which also records the command in `~/.fehbg` for restoring at login.
+pub struct Feh {
+    pub fit: FitMode,
+}
+
+impl WallpaperSetter for Feh {
+    fn id(&self) -> &'static str {
+        "feh"
+    }
+
+    fn name(&self) -> &'static str {
+        "feh"
+    }
+
+    fn detect(&self) -> Detection {
+        x11_detection("feh")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        let mode = match self.fit {
+            FitMode::Fill => "--bg-fill",
+            FitMode::Fit => "--bg-max",
+            FitMode::Stretch => "--bg-scale",
+            FitMode::Center => "--bg-center",
+            FitMode::Tile => "--bg-tile",
+        };
+        run("feh", &[mode], Some(path))
+    }
+
+    /// The last argument of the feh line in `~/.fehbg`, e.g.
+    /// `feh --no-fehbg --bg-fill '/path/to.jpg'`. feh shell-quotes the path,
+    /// so it's split the same way a shell would.
+    fn current(&self) -> Option<PathBuf> {
+        let script = fs::read_to_string(home_file(".fehbg")?).ok()?;
+        let line = script.lines().rev().find(|l| l.trim_start().starts_with("feh"))?;
+        let words = shell_words::split(line).ok()?;
+        words.last().filter(|w| !w.starts_with('-')).map(PathBuf::from)
+    }
+}
+
+/// nitrogen, which saves to its own config so `nitrogen --restore` works at login.
+pub struct Nitrogen {
+    pub fit: FitMode,
+}
+
+impl WallpaperSetter for Nitrogen {
+    fn id(&self) -> &'static str {
+        "nitrogen"
+    }
+
+    fn name(&self) -> &'static str {
+        "nitrogen"
+    }
+
+    fn detect(&self) -> Detection {
+        x11_detection("nitrogen")
+    }
+
+    fn apply(&self, path: &Path, monitor: Option<&str>) -> Result<(), AppError> {
+        let mode = match self.fit {
+            FitMode::Fill => "--set-zoom-fill",
+            FitMode::Fit => "--set-zoom",
+            FitMode::Stretch => "--set-scaled",
+            FitMode::Center => "--set-centered",
+            FitMode::Tile => "--set-tiled",
+        };
+        let head = match monitor {
+            Some(name) => {
+                let index = monitor_index(name)
+                    .ok_or_else(|| AppError::invalid_input(format!("unknown monitor: {name}")))?;
+                Some(format!("--head={index}"))
+            }
+            None => None,
+        };
+        let mut args = vec![mode, "--save"];
+        args.extend(head.as_deref());
+        run("nitrogen", &args, Some(path))
+    }
+
+    /// The first `file=` entry in `bg-saved.cfg`.
+    fn current(&self) -> Option<PathBuf> {
+        let config = fs::read_to_string(home_file(".config/nitrogen/bg-saved.cfg")?).ok()?;
+        config
+            .lines()
+            .find_map(|line| line.strip_prefix("file="))
+            .map(PathBuf::from)
+    }
+
+    fn supports_per_monitor(&self) -> bool {
+        true
+    }
+}
+
+pub struct Hsetroot {
+    pub fit: FitMode,
+}
+
+impl WallpaperSetter for Hsetroot {
+    fn id(&self) -> &'static str {
+        "hsetroot"
+    }
+
+    fn name(&self) -> &'static str {
+        "hsetroot"
+    }
+
+    fn detect(&self) -> Detection {
+        x11_detection("hsetroot")
+    }
+
+    fn apply(&self, path: &Path, _monitor: Option<&str>) -> Result<(), AppError> {
+        let mode = match self.fit {
+            FitMode::Fill => "-cover",
+            FitMode::Fit => "-full",
+            FitMode::Stretch => "-fill",
+            FitMode::Center => "-center",
+            FitMode::Tile => "-tile",
+        };
+        run("hsetroot", &[mode], Some(path))
+    }
+}
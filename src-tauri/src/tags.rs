@@ -0,0 +1,124 @@
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::error::AppError;
+use crate::http::WallhavenClient;
+use crate::query::{Query, Term};
+use crate::search::SearchParams;
+use crate::settings::load_settings;
+use crate::wallhaven::{api_key_for, api_url, fetch_details, search, Tag, Wallpaper, WallpaperPage};
+
+/// Tags per wallpaper id, so frequency counts don't refetch wallpapers already seen
+/// this session. Managed as Tauri state.
+#[derive(Default)]
+pub struct TagCache(Mutex<HashMap<String, Vec<Tag>>>);
+
+#[derive(Debug, Deserialize)]
+struct TagResponse {
+    data: Tag,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: Tag,
+    pub count: u32,
+}
+
+#[tauri::command]
+pub async fn fetch_tag(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    tag_id: u64,
+) -> Result<Tag, AppError> {
+    debug!("fetch_tag: tag_id={}", tag_id);
+    let settings = load_settings(app);
+    let url = api_url(&settings, &format!("tag/{tag_id}"));
+    let mut req = http.http().get(&url);
+    if let Some(key) = api_key_for(&settings, &url) {
+        req = req.header("X-API-Key", key);
+    }
+
+    let response = http.send("fetch_tag", req).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        warn!("fetch_tag: API returned status={}", status);
+        return Err(AppError::from_status(status));
+    }
+    let text = response
+        .text()
+        .await
+        .map_err(|e| AppError::from(e).context("reading body failed"))?;
+    let resp: TagResponse = serde_json::from_str(&text)?;
+    Ok(resp.data)
+}
+
+/// Searches for wallpapers carrying exactly this tag (`id:N`).
+#[tauri::command]
+pub async fn search_by_tag(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    tag_id: u64,
+    page: Option<u32>,
+) -> Result<WallpaperPage, AppError> {
+    let settings = load_settings(app.clone());
+    let params = SearchParams {
+        q: Some(Query::new().with(Term::TagId(tag_id)).to_string()),
+        page,
+        ..SearchParams::default()
+    };
+    search(&app, &http, &settings, &params).await
+}
+
+/// Counts tags across the given wallpapers, most frequent first. Wallpapers
+/// without tags (search listings never include them) are looked up one by one
+/// through the shared client, whose rate limiter paces a large batch instead of
+/// letting it spend the whole request budget at once.
+#[tauri::command]
+pub async fn tag_frequencies(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    cache: tauri::State<'_, TagCache>,
+    wallpapers: Vec<Wallpaper>,
+) -> Result<Vec<TagCount>, AppError> {
+    let settings = load_settings(app);
+    let mut counts: HashMap<u64, TagCount> = HashMap::new();
+    let mut failed = 0;
+
+    for wallpaper in &wallpapers {
+        let cached = cache.0.lock().unwrap().get(&wallpaper.id).cloned();
+        let tags = match cached {
+            Some(tags) => tags,
+            None if !wallpaper.tags.is_empty() => wallpaper.tags.clone(),
+            None => {
+                match fetch_details(&http, &settings, &wallpaper.id).await {
+                    Ok(details) => details.tags,
+                    Err(e) => {
+                        warn!("tag_frequencies: skipping {}: {e}", wallpaper.id);
+                        failed += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+        cache.0.lock().unwrap().insert(wallpaper.id.clone(), tags.clone());
+
+        for tag in tags {
+            counts
+                .entry(tag.id)
+                .or_insert_with(|| TagCount { tag, count: 0 })
+                .count += 1;
+        }
+    }
+
+    let mut counts: Vec<TagCount> = counts.into_values().collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.name.cmp(&b.tag.name)));
+    debug!(
+        "tag_frequencies: {} distinct tags across {} wallpapers ({} lookups failed)",
+        counts.len(),
+        wallpapers.len() - failed,
+        failed
+    );
+    Ok(counts)
+}
@@ -0,0 +1,189 @@
+use log::{debug, warn};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tauri::http::{header, Response, StatusCode, Uri};
+use tauri::Manager;
+
+use crate::cache::CacheEntry;
+use crate::download::{cache_dir, image_kind};
+use crate::error::AppError;
+use crate::http::WallhavenClient;
+use crate::settings::load_settings;
+use crate::wallhaven::image_url;
+
+/// Custom protocol serving Wallhaven thumbnails from a disk cache, as
+/// `wallthumb://{small|large|original}/{id}`.
+pub const SCHEME: &str = "wallthumb";
+
+#[derive(Debug, Clone, Copy)]
+enum ThumbSize {
+    Small,
+    Large,
+    Original,
+}
+
+impl ThumbSize {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "small" => Some(ThumbSize::Small),
+            "large" | "lg" => Some(ThumbSize::Large),
+            "original" | "orig" => Some(ThumbSize::Original),
+            _ => None,
+        }
+    }
+
+    const ALL: [ThumbSize; 3] = [ThumbSize::Small, ThumbSize::Large, ThumbSize::Original];
+
+    /// Directory name on th.wallhaven.cc.
+    fn remote_dir(self) -> &'static str {
+        match self {
+            ThumbSize::Small => "small",
+            ThumbSize::Large => "lg",
+            ThumbSize::Original => "orig",
+        }
+    }
+}
+
+/// Accepts both `wallthumb://small/abc123` and the `wallthumb://localhost/small%2Fabc123`
+/// (or `http://wallthumb.localhost/...` on Windows) form produced by `convertFileSrc`.
+fn parse_target(uri: &Uri) -> Option<(ThumbSize, String)> {
+    let host = uri.host().unwrap_or_default();
+    let path = uri.path().trim_matches('/').replace("%2F", "/").replace("%2f", "/");
+    let target = if host.is_empty() || host == "localhost" || host.starts_with(SCHEME) {
+        path
+    } else {
+        format!("{host}/{path}")
+    };
+    let (size, id) = target.split_once('/')?;
+    let id = id.trim_end_matches(".jpg");
+    if id.len() < 2 || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some((ThumbSize::parse(size)?, id.to_string()))
+}
+
+/// Where thumbnails are cached, one subdirectory per size.
+pub fn dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    Ok(cache_dir(app)?.join("thumbs"))
+}
+
+fn thumb_path(app: &tauri::AppHandle, size: ThumbSize, id: &str) -> Result<PathBuf, AppError> {
+    let dir = dir(app)?.join(size.remote_dir());
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{id}.jpg")))
+}
+
+async fn load_thumb(app: &tauri::AppHandle, size: ThumbSize, id: &str) -> Result<Vec<u8>, AppError> {
+    let path = thumb_path(app, size, id)?;
+    if let Ok(bytes) = fs::read(&path) {
+        if image_kind(&bytes).is_some() {
+            return Ok(bytes);
+        }
+        warn!("thumbs: discarding corrupt thumbnail {:?}", path);
+    }
+
+    let settings = load_settings(app.clone());
+    let url = format!("https://th.wallhaven.cc/{}/{}/{id}.jpg", size.remote_dir(), &id[..2]);
+    debug!("thumbs: fetching {}", url);
+    let http = app.state::<WallhavenClient>();
+    let response = http.http().get(image_url(&settings, &url)).send().await?;
+    if !response.status().is_success() {
+        return Err(AppError::from_status(response.status()).context("thumbnail download failed"));
+    }
+    let bytes = response.bytes().await?.to_vec();
+    if image_kind(&bytes).is_none() {
+        return Err(AppError::invalid_image("thumbnail is not an image"));
+    }
+
+    let tmp = path.with_extension("jpg.part");
+    if let Err(e) = fs::write(&tmp, &bytes).and_then(|()| fs::rename(&tmp, &path)) {
+        warn!("thumbs: couldn't cache {:?}: {e}", path);
+        fs::remove_file(&tmp).ok();
+    }
+    Ok(bytes)
+}
+
+/// Cached thumbnails of every size, aged by mtime. `.part` files are left out.
+pub fn entries(app: &tauri::AppHandle) -> Result<Vec<CacheEntry>, AppError> {
+    let dir = dir(app)?;
+    let mut entries = Vec::new();
+    for size in ThumbSize::ALL {
+        let Ok(items) = fs::read_dir(dir.join(size.remote_dir())) else {
+            continue;
+        };
+        for item in items {
+            let item = item?;
+            let path = item.path();
+            let meta = item.metadata()?;
+            if path.extension().is_none_or(|e| e != "jpg") || !meta.is_file() {
+                continue;
+            }
+            entries.push(CacheEntry {
+                path,
+                size: meta.len(),
+                last_used: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                index: None,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Wallpaper id of a cached thumbnail, e.g. `abc123` for `thumbs/lg/abc123.jpg`.
+pub fn wallpaper_id(entry: &CacheEntry) -> String {
+    entry.path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Deletes every cached size of each wallpaper's thumbnail.
+pub fn remove(app: &tauri::AppHandle, ids: &[String]) {
+    let Ok(dir) = dir(app) else {
+        return;
+    };
+    for id in ids {
+        for size in ThumbSize::ALL {
+            let path = dir.join(size.remote_dir()).join(format!("{id}.jpg"));
+            match fs::remove_file(&path) {
+                Ok(()) => debug!("thumbs: removed {:?}", path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("thumbs: couldn't remove {:?}: {e}", path),
+            }
+        }
+    }
+}
+
+/// Handler for the `wallthumb` protocol; serves from disk, fetching on a miss.
+pub fn handle(
+    app: tauri::AppHandle,
+    request: tauri::http::Request<Vec<u8>>,
+    responder: tauri::UriSchemeResponder,
+) {
+    let Some((size, id)) = parse_target(request.uri()) else {
+        responder.respond(error_response(StatusCode::BAD_REQUEST));
+        return;
+    };
+    tauri::async_runtime::spawn(async move {
+        let response = match load_thumb(&app, size, &id).await {
+            Ok(bytes) => Response::builder()
+                .header(header::CONTENT_TYPE, "image/jpeg")
+                .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+                .body(bytes)
+                .unwrap_or_else(|_| error_response(StatusCode::INTERNAL_SERVER_ERROR)),
+            Err(e) => {
+                warn!("thumbs: {:?} {} unavailable: {e}", size, id);
+                let status = match e.status {
+                    Some(404) => StatusCode::NOT_FOUND,
+                    _ => StatusCode::BAD_GATEWAY,
+                };
+                error_response(status)
+            }
+        };
+        responder.respond(response);
+    });
+}
+
+fn error_response(status: StatusCode) -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = status;
+    response
+}
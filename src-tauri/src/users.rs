@@ -0,0 +1,120 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::Manager;
+
+use crate::error::AppError;
+use crate::http::WallhavenClient;
+use crate::query::{validate_username, Query, Term};
+use crate::search::{Order, SearchParams, Sorting};
+use crate::settings::load_settings;
+use crate::wallhaven::{collection_wallpapers, collections, search, Collection, WallpaperPage};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FollowedUser {
+    pub username: String,
+    pub followed_at: String,
+}
+
+fn followed_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .expect("failed to get config dir");
+    fs::create_dir_all(&dir).ok();
+    dir.join("followed_users.json")
+}
+
+fn load_followed(app: &tauri::AppHandle) -> Vec<FollowedUser> {
+    let path = followed_path(app);
+    fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_followed(app: &tauri::AppHandle, entries: &[FollowedUser]) -> Result<(), AppError> {
+    let path = followed_path(app);
+    let json = serde_json::to_string_pretty(entries)?;
+    Ok(fs::write(&path, json)?)
+}
+
+/// Accepts `name` or `@name`.
+fn normalize(username: &str) -> Result<String, AppError> {
+    let name = username.trim().trim_start_matches('@');
+    validate_username(name)?;
+    Ok(name.to_string())
+}
+
+#[tauri::command]
+pub async fn fetch_user_collections(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    username: String,
+) -> Result<Vec<Collection>, AppError> {
+    let username = normalize(&username)?;
+    let settings = load_settings(app);
+    collections(&http, &settings, &username).await
+}
+
+#[tauri::command]
+pub async fn fetch_user_collection_wallpapers(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    username: String,
+    collection_id: u64,
+    page: Option<u32>,
+) -> Result<WallpaperPage, AppError> {
+    let username = normalize(&username)?;
+    let settings = load_settings(app.clone());
+    collection_wallpapers(&app, &http, &settings, &username, collection_id, page).await
+}
+
+/// A user's uploads, newest first, via an `@username` search.
+#[tauri::command]
+pub async fn fetch_user_uploads(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    username: String,
+    page: Option<u32>,
+) -> Result<WallpaperPage, AppError> {
+    let username = normalize(&username)?;
+    let settings = load_settings(app.clone());
+    let params = SearchParams {
+        q: Some(Query::new().with(Term::User(username)).to_string()),
+        sorting: Some(Sorting::DateAdded),
+        order: Some(Order::Desc),
+        page,
+        ..SearchParams::default()
+    };
+    search(&app, &http, &settings, &params).await
+}
+
+#[tauri::command]
+pub fn get_followed_users(app: tauri::AppHandle) -> Vec<FollowedUser> {
+    load_followed(&app)
+}
+
+#[tauri::command]
+pub fn follow_user(app: tauri::AppHandle, username: String) -> Result<Vec<FollowedUser>, AppError> {
+    let username = normalize(&username)?;
+    let mut entries = load_followed(&app);
+    if !entries.iter().any(|e| e.username.eq_ignore_ascii_case(&username)) {
+        info!("follow_user: {}", username);
+        entries.push(FollowedUser {
+            username,
+            followed_at: chrono::Utc::now().to_rfc3339(),
+        });
+        save_followed(&app, &entries)?;
+    }
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn unfollow_user(app: tauri::AppHandle, username: String) -> Result<Vec<FollowedUser>, AppError> {
+    let username = normalize(&username)?;
+    let mut entries = load_followed(&app);
+    entries.retain(|e| !e.username.eq_ignore_ascii_case(&username));
+    save_followed(&app, &entries)?;
+    Ok(entries)
+}
@@ -1,17 +1,92 @@
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use tauri::Manager;
+use std::collections::HashMap;
+
+use crate::cache::CycleCollection;
+use crate::download::DownloadManager;
+use crate::error::{AppError, ErrorKind};
+use crate::http::WallhavenClient;
+use crate::query::{Query, Term};
+use crate::search::SearchParams;
+use crate::searchcache;
+use crate::settings::{load_settings, Settings};
+
+const DEFAULT_API_BASE: &str = "https://wallhaven.cc/api/v1";
+
+/// Environment overrides take precedence over Settings so fixtures and proxies
+/// can be swapped in without touching the user's config.
+const API_BASE_ENV: &str = "WALLCHEMYBAR_API_URL";
+const IMAGE_BASE_ENV: &str = "WALLCHEMYBAR_IMAGE_URL";
+
+fn env_or_setting(var: &str, setting: &str) -> Option<String> {
+    std::env::var(var)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .or_else(|| Some(setting.trim().to_string()).filter(|v| !v.is_empty()))
+        .map(|v| v.trim_end_matches('/').to_string())
+}
+
+pub fn api_url(settings: &Settings, endpoint: &str) -> String {
+    let base = env_or_setting(API_BASE_ENV, &settings.api_base_url)
+        .unwrap_or_else(|| DEFAULT_API_BASE.to_string());
+    format!("{base}/{}", endpoint.trim_start_matches('/'))
+}
 
-use crate::settings::load_settings;
+/// Rewrites a `*.wallhaven.cc` image or thumbnail URL onto the configured image host,
+/// keeping the path (`/full/…`, `/small/…`, `/lg/…`) intact.
+pub fn image_url(settings: &Settings, url: &str) -> String {
+    let Some(base) = env_or_setting(IMAGE_BASE_ENV, &settings.image_base_url) else {
+        return url.to_string();
+    };
+    let Some((_, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    if host == "wallhaven.cc" || host.ends_with(".wallhaven.cc") {
+        format!("{base}{path}")
+    } else {
+        url.to_string()
+    }
+}
+
+/// Hosts trusted with the user's API key. Mirrors and proxies configured
+/// through the base URL settings or their env overrides never get it.
+const API_KEY_HOSTS: [&str; 2] = ["wallhaven.cc", "w.wallhaven.cc"];
+
+pub fn sends_api_key(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| API_KEY_HOSTS.contains(&h)))
+        .unwrap_or(false)
+}
+
+/// The configured API key, if one is set and `url` is on Wallhaven itself.
+pub fn api_key_for<'a>(settings: &'a Settings, url: &str) -> Option<&'a str> {
+    Some(settings.api_key.trim()).filter(|k| !k.is_empty() && sends_api_key(url))
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Tag {
     pub id: u64,
     pub name: String,
+    /// Comma-separated alternative names.
+    #[serde(default)]
+    pub alias: String,
+    #[serde(default)]
+    pub category_id: u64,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub purity: String,
+    #[serde(default)]
+    pub created_at: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A Wallhaven wallpaper record. Search and collection listings fill everything
+/// except `tags` and `uploader`, which only `/w/{id}` returns. Every field past
+/// `resolution` defaults so queue entries saved by older versions still load.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Wallpaper {
     pub id: String,
     pub url: String,
@@ -20,9 +95,50 @@ pub struct Wallpaper {
     pub resolution: String,
     #[serde(default)]
     pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub short_url: String,
+    #[serde(default)]
+    pub views: u64,
+    #[serde(default)]
+    pub favorites: u64,
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub purity: String,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub dimension_x: u32,
+    #[serde(default)]
+    pub dimension_y: u32,
+    #[serde(default)]
+    pub ratio: String,
+    /// Size of the full image in bytes.
+    #[serde(default)]
+    pub file_size: u64,
+    /// MIME type of the full image, e.g. `image/jpeg`.
+    #[serde(default)]
+    pub file_type: String,
+    #[serde(default)]
+    pub created_at: String,
+    /// Dominant colours as `#rrggbb`.
+    #[serde(default)]
+    pub colors: Vec<String>,
+    #[serde(default)]
+    pub uploader: Option<Uploader>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Uploader {
+    pub username: String,
+    #[serde(default)]
+    pub group: String,
+    /// Avatar URLs keyed by size, e.g. `"128px"`.
+    #[serde(default)]
+    pub avatar: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Thumbs {
     pub large: String,
     pub original: String,
@@ -41,16 +157,89 @@ pub struct Collection {
 #[derive(Debug, Deserialize)]
 struct SearchResponse {
     data: Vec<Wallpaper>,
+    #[serde(default)]
+    meta: Option<SearchMeta>,
 }
 
 #[derive(Debug, Deserialize)]
-struct WallpaperInfoResponse {
-    data: WallpaperInfo,
+struct SearchMeta {
+    current_page: u32,
+    last_page: u32,
+    #[serde(deserialize_with = "de_u32_lenient")]
+    per_page: u32,
+    total: u64,
+    /// A string for plain searches, `{ id, tag }` for `id:` tag searches.
+    #[serde(default)]
+    query: Option<serde_json::Value>,
+    #[serde(default)]
+    seed: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WallpaperInfo {
-    pub tags: Vec<Tag>,
+/// Wallhaven reports `per_page` as a number on some endpoints and a string on others.
+fn de_u32_lenient<'de, D: serde::Deserializer<'de>>(d: D) -> Result<u32, D::Error> {
+    match serde_json::Value::deserialize(d)? {
+        serde_json::Value::Number(n) => n
+            .as_u64()
+            .map(|n| n as u32)
+            .ok_or_else(|| serde::de::Error::custom("per_page out of range")),
+        serde_json::Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+        other => Err(serde::de::Error::custom(format!("unexpected per_page: {other}"))),
+    }
+}
+
+/// One page of search or collection results plus Wallhaven's pagination metadata.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WallpaperPage {
+    pub wallpapers: Vec<Wallpaper>,
+    pub current_page: u32,
+    pub last_page: u32,
+    pub per_page: u32,
+    pub total: u64,
+    /// Seed the server used for `random` sorting; pass it back to keep paging stable.
+    pub seed: Option<String>,
+    /// The query as the server resolved it (tag name for `id:` searches).
+    pub query: Option<String>,
+    /// Served from the result cache because the network failed; may be out of date.
+    #[serde(default)]
+    pub stale: bool,
+}
+
+impl SearchResponse {
+    fn into_page(self, requested_page: u32) -> WallpaperPage {
+        let Some(meta) = self.meta else {
+            let count = self.data.len();
+            return WallpaperPage {
+                wallpapers: self.data,
+                current_page: requested_page,
+                last_page: requested_page,
+                per_page: count as u32,
+                total: count as u64,
+                seed: None,
+                query: None,
+                stale: false,
+            };
+        };
+        let query = meta.query.and_then(|q| match q {
+            serde_json::Value::String(s) => Some(s),
+            serde_json::Value::Object(o) => o.get("tag").and_then(|t| t.as_str()).map(str::to_string),
+            _ => None,
+        });
+        WallpaperPage {
+            wallpapers: self.data,
+            current_page: meta.current_page,
+            last_page: meta.last_page,
+            per_page: meta.per_page,
+            total: meta.total,
+            seed: meta.seed,
+            query,
+            stale: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WallpaperDetailsResponse {
+    data: Wallpaper,
 }
 
 #[derive(Debug, Deserialize)]
@@ -58,212 +247,179 @@ struct CollectionsResponse {
     data: Vec<Collection>,
 }
 
-fn build_client() -> Result<reqwest::Client, String> {
-    reqwest::Client::builder()
-        .user_agent("wallchemybar/0.1.0")
-        .build()
-        .map_err(|e| format!("client error: {e}"))
+fn search_request(
+    http: &WallhavenClient,
+    settings: &Settings,
+    params: &SearchParams,
+    api_key: Option<&str>,
+) -> reqwest::RequestBuilder {
+    let mut req = http.http().get(api_url(settings, "search")).query(&params.to_query());
+    if let Some(key) = api_key {
+        req = req.header("X-API-Key", key);
+    }
+    req
 }
 
 #[tauri::command]
 pub async fn fetch_search(
     app: tauri::AppHandle,
-    sorting: String,
-    page: Option<u32>,
-    query: Option<String>,
-    seed: Option<String>,
-) -> Result<Vec<Wallpaper>, String> {
-    let page_num = page.unwrap_or(1);
-    info!("fetch_search: sorting={}, page={}, query={:?}, seed={:?}", sorting, page_num, query, seed);
+    http: tauri::State<'_, WallhavenClient>,
+    params: SearchParams,
+) -> Result<WallpaperPage, AppError> {
+    let settings = load_settings(app.clone());
+    search(&app, &http, &settings, &params).await
+}
 
-    let settings = load_settings(app);
-    let client = build_client()?;
-    let page_str = page_num.to_string();
-    let purity = settings.purity.clone();
-    let categories = settings.categories.clone();
-    let atleast = settings.atleast.clone();
-    let ratios = settings.ratios.clone();
-    let query_str = query.unwrap_or_default();
-    let mut req = client.get("https://wallhaven.cc/api/v1/search").query(&[
-        ("sorting", sorting.as_str()),
-        ("purity", purity.as_str()),
-        ("categories", categories.as_str()),
-        ("page", page_str.as_str()),
-    ]);
-    if let Some(s) = &seed {
-        req = req.query(&[("seed", s.as_str())]);
-    }
-    if !query_str.is_empty() {
-        req = req.query(&[("q", query_str.as_str())]);
-    }
-    if !atleast.is_empty() {
-        req = req.query(&[("atleast", atleast.as_str())]);
-    }
-    if !ratios.is_empty() {
-        req = req.query(&[("ratios", ratios.as_str())]);
+/// Runs a search with `params` layered over the Settings defaults, going
+/// through the result cache.
+pub async fn search(
+    app: &tauri::AppHandle,
+    http: &WallhavenClient,
+    settings: &Settings,
+    params: &SearchParams,
+) -> Result<WallpaperPage, AppError> {
+    let mut params = settings.search_params().merged_with(params);
+    let page_num = *params.page.get_or_insert(1);
+    // Reject malformed terms here rather than letting the API silently ignore them
+    let mut query = Query::parse(params.q.as_deref().unwrap_or_default())?;
+    // Exact tag searches can't carry other terms, so the blacklist is skipped for them
+    if !query.terms.iter().any(|t| matches!(t, Term::TagId(_))) {
+        for tag in settings.tag_blacklist.iter().filter(|t| !t.trim().is_empty() && !t.contains('"')) {
+            let term = Term::Exclude(tag.trim().to_string());
+            if !query.terms.contains(&term) {
+                query.terms.push(term);
+            }
+        }
     }
-
-    let api_key = settings.api_key.trim().to_string();
-    if !api_key.is_empty() {
-        req = req.header("X-API-Key", &api_key);
+    params.q = Some(query.to_string()).filter(|q| !q.is_empty());
+    info!("fetch_search: params={:?}", params);
+
+    let url = api_url(settings, "search");
+    let api_key = api_key_for(settings, &url);
+    let ttl = settings.result_ttl.for_sorting(params.sorting);
+    let key = searchcache::key(&url, &params.to_query(), api_key);
+    let fetch = search_remote(http, settings, &params, page_num, api_key);
+    match searchcache::cached(app, &key, ttl, fetch).await {
+        // If unauthorized (bad API key), retry anonymously, which only allows SFW.
+        // The retry is cached as the anonymous request it is.
+        Err(e) if e.status == Some(401) && api_key.is_some() => {
+            warn!("fetch_search: got 401, retrying without API key");
+            params.purity = Some("100".to_string());
+            let key = searchcache::key(&url, &params.to_query(), None);
+            let fetch = search_remote(http, settings, &params, page_num, None);
+            searchcache::cached(app, &key, ttl, fetch).await
+        }
+        result => result,
     }
+}
 
-    let response = req
-        .send()
+async fn search_remote(
+    http: &WallhavenClient,
+    settings: &Settings,
+    params: &SearchParams,
+    page_num: u32,
+    api_key: Option<&str>,
+) -> Result<WallpaperPage, AppError> {
+    let req = search_request(http, settings, params, api_key);
+
+    let response = http
+        .send("fetch_search", req)
         .await
         .map_err(|e| {
             error!("fetch_search: request failed: {e}");
-            format!("request failed: {e}")
+            e
         })?;
 
     debug!("fetch_search: response status={}", response.status());
 
-    // If unauthorized (bad API key), retry without it
-    let text = if response.status() == 401 && !api_key.is_empty() {
-        warn!("fetch_search: got 401, retrying without API key");
-        let mut retry = client.get("https://wallhaven.cc/api/v1/search").query(&[
-            ("sorting", sorting.as_str()),
-            ("purity", "100"),
-            ("categories", categories.as_str()),
-            ("page", page_str.as_str()),
-        ]);
-        if let Some(s) = &seed {
-            retry = retry.query(&[("seed", s.as_str())]);
-        }
-        if !query_str.is_empty() {
-            retry = retry.query(&[("q", query_str.as_str())]);
-        }
-        if !atleast.is_empty() {
-            retry = retry.query(&[("atleast", atleast.as_str())]);
-        }
-        if !ratios.is_empty() {
-            retry = retry.query(&[("ratios", ratios.as_str())]);
-        }
-        retry
-            .send()
-            .await
-            .map_err(|e| {
-                error!("fetch_search: retry request failed: {e}");
-                format!("request failed: {e}")
-            })?
-            .text()
-            .await
-            .map_err(|e| {
-                error!("fetch_search: retry reading body failed: {e}");
-                format!("reading body failed: {e}")
-            })?
-    } else if !response.status().is_success() {
+    if !response.status().is_success() {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
         error!("fetch_search: API returned status={}, body={}", status, &body[..body.len().min(500)]);
-        return Err(format!("API error: status {status}"));
-    } else {
-        response
-            .text()
-            .await
-            .map_err(|e| {
-                error!("fetch_search: reading body failed: {e}");
-                format!("reading body failed: {e}")
-            })?
-    };
+        return Err(AppError::from_status(status));
+    }
+
+    let text = response
+        .text()
+        .await
+        .map_err(|e| {
+            error!("fetch_search: reading body failed: {e}");
+            AppError::from(e).context("reading body failed")
+        })?;
 
     let resp: SearchResponse =
         serde_json::from_str(&text).map_err(|e| {
             error!("fetch_search: parse failed: {e}, body={}", &text[..text.len().min(500)]);
-            format!("parse failed: {e}")
+            AppError::from(e)
         })?;
 
-    info!("fetch_search: returned {} wallpapers for page {}", resp.data.len(), page_num);
-    Ok(resp.data)
+    let page = resp.into_page(page_num);
+    info!(
+        "fetch_search: returned {} wallpapers for page {}/{} (total {})",
+        page.wallpapers.len(), page.current_page, page.last_page, page.total
+    );
+    Ok(page)
 }
 
 #[tauri::command]
-pub async fn fetch_collections(app: tauri::AppHandle) -> Result<Vec<Collection>, String> {
+pub async fn fetch_collections(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+) -> Result<Vec<Collection>, AppError> {
     let settings = load_settings(app);
     let username = settings.username.trim().to_string();
     if username.is_empty() {
-        return Err("Username not configured. Set it in Settings.".into());
+        return Err(AppError::invalid_input("Username not configured. Set it in Settings."));
     }
+    collections(&http, &settings, &username).await
+}
 
-    let client = build_client()?;
-    let mut req = client.get(format!(
-        "https://wallhaven.cc/api/v1/collections/{username}"
-    ));
-
-    let api_key = settings.api_key.trim().to_string();
-    if !api_key.is_empty() {
-        req = req.header("X-API-Key", &api_key);
+/// Lists `username`'s collections. Other users' private collections are omitted by the API.
+pub async fn collections(
+    http: &WallhavenClient,
+    settings: &Settings,
+    username: &str,
+) -> Result<Vec<Collection>, AppError> {
+    let url = api_url(settings, &format!("collections/{username}"));
+    let mut req = http.http().get(&url);
+    if let Some(key) = api_key_for(settings, &url) {
+        req = req.header("X-API-Key", key);
     }
 
-    let text = req
-        .send()
-        .await
-        .map_err(|e| format!("request failed: {e}"))?
+    let response = http.send("fetch_collections", req).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        warn!("fetch_collections: API returned status={} for {}", status, username);
+        return Err(AppError::from_status(status));
+    }
+    let text = response
         .text()
         .await
-        .map_err(|e| format!("reading body failed: {e}"))?;
+        .map_err(|e| AppError::from(e).context("reading body failed"))?;
 
-    let resp: CollectionsResponse =
-        serde_json::from_str(&text).map_err(|e| format!("parse failed: {e}"))?;
+    let resp: CollectionsResponse = serde_json::from_str(&text)?;
 
     Ok(resp.data)
 }
 
 #[tauri::command]
-pub async fn set_wallpaper(app: tauri::AppHandle, wallpaper: Wallpaper) -> Result<(), String> {
+pub async fn set_wallpaper(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    downloads: tauri::State<'_, DownloadManager>,
+    wallpaper: Wallpaper,
+) -> Result<(), AppError> {
     info!("set_wallpaper: id={}, path={}", wallpaper.id, wallpaper.path);
 
-    let filename = wallpaper.path.rsplit('/').next().unwrap_or("wallpaper.jpg");
-
-    let cache_dir = app
-        .path()
-        .app_cache_dir()
-        .map_err(|e| format!("cache dir error: {e}"))?;
-    fs::create_dir_all(&cache_dir).ok();
-    let file_path = cache_dir.join(filename);
-
-    if file_path.exists() {
-        debug!("set_wallpaper: using cached file {:?}", file_path);
-    } else {
-        info!("set_wallpaper: downloading to {:?}", file_path);
-        let client = build_client()?;
-
-        let settings = load_settings(app.clone());
-        let api_key = settings.api_key.trim().to_string();
-
-        let mut req = client.get(&wallpaper.path);
-        if !api_key.is_empty() {
-            req = req.header("X-API-Key", &api_key);
-        }
-
-        let response = req
-            .send()
-            .await
-            .map_err(|e| {
-                error!("set_wallpaper: download failed: {e}");
-                format!("download failed: {e}")
-            })?;
-
-        debug!("set_wallpaper: download status={}", response.status());
-
-        let bytes = response
-            .bytes()
-            .await
-            .map_err(|e| {
-                error!("set_wallpaper: reading image failed: {e}");
-                format!("reading image failed: {e}")
-            })?;
-
-        info!("set_wallpaper: downloaded {} bytes", bytes.len());
-        fs::write(&file_path, &bytes).map_err(|e| {
-            error!("set_wallpaper: write failed: {e}");
-            format!("write failed: {e}")
-        })?;
-    }
+    ensure_uploader_allowed(&http, &load_settings(app.clone()), &wallpaper).await?;
+    let file_path = downloads.fetch(&app, &http, &wallpaper).await?;
 
     let settings = crate::settings::load_settings(app.clone());
-    crate::setwallpaper::set(file_path.to_str().unwrap(), &settings.linux_wallpaper_cmd)?;
+    crate::setwallpaper::set(file_path, settings).await?;
     crate::history::add_to_history(&app, &wallpaper)?;
+    if let Err(e) = crate::cache::enforce_limits(&app) {
+        warn!("set_wallpaper: cache cleanup failed: {e}");
+    }
 
     info!("set_wallpaper: applied successfully");
     Ok(())
@@ -272,38 +428,69 @@ pub async fn set_wallpaper(app: tauri::AppHandle, wallpaper: Wallpaper) -> Resul
 #[tauri::command]
 pub async fn fetch_collection_wallpapers(
     app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    cycle: tauri::State<'_, CycleCollection>,
     collection_id: u64,
     page: Option<u32>,
-) -> Result<Vec<Wallpaper>, String> {
-    info!("fetch_collection_wallpapers: collection_id={}, page={:?}", collection_id, page);
-
-    let settings = load_settings(app);
+) -> Result<WallpaperPage, AppError> {
+    let settings = load_settings(app.clone());
     let username = settings.username.trim().to_string();
     if username.is_empty() {
         warn!("fetch_collection_wallpapers: username not configured");
-        return Err("Username not configured. Set it in Settings.".into());
+        return Err(AppError::invalid_input("Username not configured. Set it in Settings."));
     }
+    let result = collection_wallpapers(&app, &http, &settings, &username, collection_id, page).await?;
+    if collection_id == settings.collection_cycle_collection_id {
+        cycle.remember(collection_id, &result.wallpapers);
+    }
+    Ok(result)
+}
 
-    let client = build_client()?;
-    let mut req = client.get(format!(
-        "https://wallhaven.cc/api/v1/collections/{username}/{collection_id}"
-    ));
+/// One page of a collection, going through the result cache.
+pub async fn collection_wallpapers(
+    app: &tauri::AppHandle,
+    http: &WallhavenClient,
+    settings: &Settings,
+    username: &str,
+    collection_id: u64,
+    page: Option<u32>,
+) -> Result<WallpaperPage, AppError> {
+    let url = api_url(settings, &format!("collections/{username}/{collection_id}"));
+    let query = [("page", page.unwrap_or(1).to_string())];
+    let key = searchcache::key(&url, &query, api_key_for(settings, &url));
+    let ttl = settings.result_ttl.for_collection();
+    let fetch = collection_wallpapers_remote(http, settings, username, collection_id, page);
+    searchcache::cached(app, &key, ttl, fetch).await
+}
 
-    let api_key = settings.api_key.trim().to_string();
-    if !api_key.is_empty() {
-        req = req.header("X-API-Key", &api_key);
+async fn collection_wallpapers_remote(
+    http: &WallhavenClient,
+    settings: &Settings,
+    username: &str,
+    collection_id: u64,
+    page: Option<u32>,
+) -> Result<WallpaperPage, AppError> {
+    info!(
+        "fetch_collection_wallpapers: username={}, collection_id={}, page={:?}",
+        username, collection_id, page
+    );
+
+    let url = api_url(settings, &format!("collections/{username}/{collection_id}"));
+    let mut req = http.http().get(&url);
+    if let Some(key) = api_key_for(settings, &url) {
+        req = req.header("X-API-Key", key);
     }
 
     if let Some(p) = page {
         req = req.query(&[("page", p.to_string())]);
     }
 
-    let response = req
-        .send()
+    let response = http
+        .send("fetch_collection_wallpapers", req)
         .await
         .map_err(|e| {
             error!("fetch_collection_wallpapers: request failed: {e}");
-            format!("request failed: {e}")
+            e
         })?;
 
     debug!("fetch_collection_wallpapers: response status={}", response.status());
@@ -312,7 +499,7 @@ pub async fn fetch_collection_wallpapers(
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
         error!("fetch_collection_wallpapers: API returned status={}, body={}", status, &body[..body.len().min(500)]);
-        return Err(format!("API error: status {status}"));
+        return Err(AppError::from_status(status));
     }
 
     let text = response
@@ -320,83 +507,196 @@ pub async fn fetch_collection_wallpapers(
         .await
         .map_err(|e| {
             error!("fetch_collection_wallpapers: reading body failed: {e}");
-            format!("reading body failed: {e}")
+            AppError::from(e).context("reading body failed")
         })?;
 
     let resp: SearchResponse =
         serde_json::from_str(&text).map_err(|e| {
             error!("fetch_collection_wallpapers: parse failed: {e}, body={}", &text[..text.len().min(500)]);
-            format!("parse failed: {e}")
+            AppError::from(e)
         })?;
 
-    info!("fetch_collection_wallpapers: returned {} wallpapers", resp.data.len());
-    Ok(resp.data)
+    let page = resp.into_page(page.unwrap_or(1));
+    info!(
+        "fetch_collection_wallpapers: returned {} wallpapers for page {}/{}",
+        page.wallpapers.len(), page.current_page, page.last_page
+    );
+    Ok(page)
 }
 
 #[tauri::command]
-pub async fn validate_api_key(api_key: String) -> Result<bool, String> {
+pub async fn validate_api_key(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    api_key: String,
+) -> Result<bool, AppError> {
     let trimmed = api_key.trim();
     if trimmed.is_empty() {
         return Ok(false);
     }
-    let client = build_client()?;
-    let response = client
-        .get("https://wallhaven.cc/api/v1/settings")
-        .header("X-API-Key", trimmed)
-        .send()
-        .await
-        .map_err(|e| format!("request failed: {e}"))?;
+    let settings = load_settings(app);
+    let url = api_url(&settings, "settings");
+    if !sends_api_key(&url) {
+        return Err(AppError::invalid_input(
+            "API keys are only sent to wallhaven.cc, not the configured API URL",
+        ));
+    }
+    let req = http.http().get(&url).header("X-API-Key", trimmed);
+    let response = http.send("validate_api_key", req).await?;
     debug!("validate_api_key: status={}", response.status());
     Ok(response.status().as_u16() == 200)
 }
 
-#[tauri::command]
-pub async fn fetch_wallpaper_tags(
-    app: tauri::AppHandle,
-    wallpaper_id: String,
-) -> Result<Vec<Tag>, String> {
-    debug!("fetch_wallpaper_tags: wallpaper_id={}", wallpaper_id);
-
-    let settings = load_settings(app);
-    let client = build_client()?;
-
-    let mut req = client.get(format!(
-        "https://wallhaven.cc/api/v1/w/{wallpaper_id}"
-    ));
-
-    let api_key = settings.api_key.trim().to_string();
-    if !api_key.is_empty() {
-        req = req.header("X-API-Key", &api_key);
+pub async fn fetch_details(
+    http: &WallhavenClient,
+    settings: &Settings,
+    wallpaper_id: &str,
+) -> Result<Wallpaper, AppError> {
+    let url = api_url(settings, &format!("w/{wallpaper_id}"));
+    let mut req = http.http().get(&url);
+    if let Some(key) = api_key_for(settings, &url) {
+        req = req.header("X-API-Key", key);
     }
 
-    let response = req
-        .send()
+    let response = http
+        .send("fetch_wallpaper_details", req)
         .await
         .map_err(|e| {
-            error!("fetch_wallpaper_tags: request failed: {e}");
-            format!("request failed: {e}")
+            error!("fetch_wallpaper_details: request failed: {e}");
+            e
         })?;
 
     if !response.status().is_success() {
         let status = response.status();
-        warn!("fetch_wallpaper_tags: API returned status={}", status);
-        return Err(format!("API error: status {status}"));
+        warn!("fetch_wallpaper_details: API returned status={}", status);
+        return Err(AppError::from_status(status));
     }
 
     let text = response
         .text()
         .await
         .map_err(|e| {
-            error!("fetch_wallpaper_tags: reading body failed: {e}");
-            format!("reading body failed: {e}")
+            error!("fetch_wallpaper_details: reading body failed: {e}");
+            AppError::from(e).context("reading body failed")
         })?;
 
-    let resp: WallpaperInfoResponse =
+    let resp: WallpaperDetailsResponse =
         serde_json::from_str(&text).map_err(|e| {
-            error!("fetch_wallpaper_tags: parse failed: {e}");
-            format!("parse failed: {e}")
+            error!("fetch_wallpaper_details: parse failed: {e}");
+            AppError::from(e)
         })?;
 
-    debug!("fetch_wallpaper_tags: returned {} tags", resp.data.tags.len());
-    Ok(resp.data.tags)
+    check_uploader(settings, &resp.data)?;
+    Ok(resp.data)
+}
+
+/// Refuses a wallpaper whose uploader is on the account's user blacklist.
+fn check_uploader(settings: &Settings, wallpaper: &Wallpaper) -> Result<(), AppError> {
+    let Some(uploader) = &wallpaper.uploader else {
+        return Ok(());
+    };
+    let blacklisted = settings
+        .user_blacklist
+        .iter()
+        .any(|name| name.trim().eq_ignore_ascii_case(&uploader.username));
+    if blacklisted {
+        info!("blacklist: refusing {} by {}", wallpaper.id, uploader.username);
+        return Err(AppError::blacklisted(format!(
+            "{} is on your user blacklist",
+            uploader.username
+        )));
+    }
+    Ok(())
+}
+
+/// Checks `wallpaper`'s uploader against the user blacklist before it's set or
+/// queued. Listings don't say who uploaded a wallpaper, so it's looked up when
+/// the blacklist isn't empty; if that lookup fails the wallpaper is let through.
+pub async fn ensure_uploader_allowed(
+    http: &WallhavenClient,
+    settings: &Settings,
+    wallpaper: &Wallpaper,
+) -> Result<(), AppError> {
+    if settings.user_blacklist.iter().all(|name| name.trim().is_empty()) {
+        return Ok(());
+    }
+    if wallpaper.uploader.is_some() {
+        return check_uploader(settings, wallpaper);
+    }
+    match fetch_details(http, settings, &wallpaper.id).await {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind == ErrorKind::Blacklisted => Err(e),
+        Err(e) => {
+            warn!("blacklist: couldn't look up the uploader of {}: {e}", wallpaper.id);
+            Ok(())
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn fetch_wallpaper_details(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    wallpaper_id: String,
+) -> Result<Wallpaper, AppError> {
+    debug!("fetch_wallpaper_details: wallpaper_id={}", wallpaper_id);
+    let settings = load_settings(app);
+    let details = fetch_details(&http, &settings, &wallpaper_id).await?;
+    debug!(
+        "fetch_wallpaper_details: {} is {} bytes, {} tags",
+        details.id, details.file_size, details.tags.len()
+    );
+    Ok(details)
+}
+
+#[tauri::command]
+pub async fn fetch_wallpaper_tags(
+    app: tauri::AppHandle,
+    http: tauri::State<'_, WallhavenClient>,
+    wallpaper_id: String,
+) -> Result<Vec<Tag>, AppError> {
+    debug!("fetch_wallpaper_tags: wallpaper_id={}", wallpaper_id);
+
+    let settings = load_settings(app);
+    let details = fetch_details(&http, &settings, &wallpaper_id).await?;
+
+    debug!("fetch_wallpaper_tags: returned {} tags", details.tags.len());
+    Ok(details.tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn by(username: &str) -> Wallpaper {
+        Wallpaper {
+            id: "abc123".into(),
+            uploader: Some(Uploader {
+                username: username.into(),
+                group: "User".into(),
+                avatar: HashMap::new(),
+            }),
+            ..Wallpaper::default()
+        }
+    }
+
+    #[test]
+    fn check_uploader_refuses_blacklisted_users() {
+        let settings = Settings {
+            user_blacklist: vec![" SomeUser ".into()],
+            ..Settings::default()
+        };
+        let err = check_uploader(&settings, &by("someuser")).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Blacklisted);
+        assert!(check_uploader(&settings, &by("other")).is_ok());
+    }
+
+    #[test]
+    fn check_uploader_allows_unknown_uploaders() {
+        let settings = Settings {
+            user_blacklist: vec!["someuser".into()],
+            ..Settings::default()
+        };
+        assert!(check_uploader(&settings, &Wallpaper::default()).is_ok());
+    }
 }
@@ -0,0 +1,16 @@
+import type { BandwidthUsage, Wallpaper } from "$lib/types";
+
+/**
+ * Picks the next wallpaper to cycle to on a metered connection: the first of
+ * `candidates` within the size cap, else the smallest one. Returns null when
+ * nothing fits in the remaining budget.
+ */
+export function pickForBudget(candidates: Wallpaper[], usage: BandwidthUsage): Wallpaper | null {
+    const size = (w: Wallpaper) => w.file_size ?? 0;
+    const affordable = candidates.filter(
+        (w) => usage.remaining_bytes === null || size(w) <= usage.remaining_bytes,
+    );
+    if (affordable.length === 0) return null;
+    const small = affordable.find((w) => !usage.max_file_bytes || size(w) <= usage.max_file_bytes);
+    return small ?? affordable.reduce((a, b) => (size(b) < size(a) ? b : a));
+}
@@ -0,0 +1,35 @@
+export type ErrorKind =
+    | "network"
+    | "unauthorized"
+    | "rate_limited"
+    | "not_found"
+    | "parse"
+    | "io"
+    | "invalid_input"
+    | "unsupported_desktop"
+    | "setter_failed"
+    | "invalid_image"
+    | "cancelled"
+    | "budget_exceeded"
+    | "blacklisted";
+
+/** Shape of the error every backend command rejects with. */
+export interface AppError {
+    kind: ErrorKind;
+    status: number | null;
+    retry_after: number | null;
+    message: string;
+}
+
+export function isAppError(e: unknown): e is AppError {
+    return typeof e === "object" && e !== null && "kind" in e && "message" in e;
+}
+
+export function errorMessage(e: unknown): string {
+    return isAppError(e) ? e.message : String(e);
+}
+
+/** True when the backend couldn't reach Wallhaven at all (no HTTP response). */
+export function isOfflineError(e: unknown): boolean {
+    return isAppError(e) && e.kind === "network" && (e.status === null || e.status >= 500);
+}
@@ -0,0 +1,11 @@
+export function formatBytes(bytes: number | undefined): string {
+    if (!bytes) return "";
+    const units = ["B", "KB", "MB", "GB"];
+    let value = bytes;
+    let unit = 0;
+    while (value >= 1024 && unit < units.length - 1) {
+        value /= 1024;
+        unit++;
+    }
+    return `${value.toFixed(unit >= 2 ? 1 : 0)} ${units[unit]}`;
+}
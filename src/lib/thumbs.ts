@@ -0,0 +1,8 @@
+import { convertFileSrc } from "@tauri-apps/api/core";
+
+export type ThumbSize = "small" | "large" | "original";
+
+/** Thumbnail served from the backend's disk cache through the `wallthumb` protocol. */
+export function thumbSrc(id: string, size: ThumbSize = "small"): string {
+    return convertFileSrc(`${size}/${id}`, "wallthumb");
+}
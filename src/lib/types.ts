@@ -1,6 +1,16 @@
 export interface Tag {
     id: number;
     name: string;
+    alias?: string;
+    category_id?: number;
+    category?: string;
+    purity?: string;
+    created_at?: string;
+}
+
+export interface TagCount {
+    tag: Tag;
+    count: number;
 }
 
 export interface Wallpaper {
@@ -10,6 +20,39 @@ export interface Wallpaper {
     thumbs: { large: string; original: string; small: string };
     resolution: string;
     tags?: Tag[];
+    short_url?: string;
+    views?: number;
+    favorites?: number;
+    source?: string;
+    purity?: string;
+    category?: string;
+    dimension_x?: number;
+    dimension_y?: number;
+    ratio?: string;
+    file_size?: number;
+    file_type?: string;
+    created_at?: string;
+    colors?: string[];
+    uploader?: Uploader | null;
+}
+
+export interface Uploader {
+    username: string;
+    group: string;
+    avatar: Record<string, string>;
+}
+
+/** One page of results plus Wallhaven's pagination metadata. */
+export interface WallpaperPage {
+    wallpapers: Wallpaper[];
+    current_page: number;
+    last_page: number;
+    per_page: number;
+    total: number;
+    seed: string | null;
+    query: string | null;
+    /** Served from the on-disk cache because the network failed. */
+    stale?: boolean;
 }
 
 export interface Collection {
@@ -28,3 +71,102 @@ export type View =
     | { kind: "history" }
     | { kind: "queue" }
     | { kind: "settings" };
+
+export type Sorting = "date_added" | "relevance" | "random" | "views" | "favorites" | "toplist" | "hot";
+
+export type TopRange = "1d" | "3d" | "1w" | "1M" | "3M" | "6M" | "1y";
+
+/** Overrides for a single `fetch_search` call; unset fields fall back to Settings. */
+export interface SearchParams {
+    q?: string | null;
+    categories?: string | null;
+    purity?: string | null;
+    sorting?: Sorting | null;
+    order?: "desc" | "asc" | null;
+    top_range?: TopRange | null;
+    atleast?: string | null;
+    resolutions?: string[] | null;
+    ratios?: string[] | null;
+    colors?: string | null;
+    ai_art_filter?: boolean | null;
+    page?: number | null;
+    seed?: string | null;
+}
+
+/** One term of a Wallhaven query; see `parse_query` / `format_query`. */
+export type QueryTerm =
+    | { kind: "keyword"; value: string }
+    | { kind: "include"; value: string }
+    | { kind: "exclude"; value: string }
+    | { kind: "user"; value: string }
+    | { kind: "tag_id"; value: number }
+    | { kind: "file_type"; value: "png" | "jpg" }
+    | { kind: "like"; value: string };
+
+export interface Query {
+    terms: QueryTerm[];
+}
+
+export interface FollowedUser {
+    username: string;
+    followed_at: string;
+}
+
+export interface DownloadProgress {
+    wallpaper_id: string;
+    state: "started" | "progress" | "done" | "failed" | "cancelled";
+    received: number;
+    total: number | null;
+}
+
+export interface CachedImage {
+    id: string;
+    source: string;
+    filename: string;
+    size: number;
+    width: number;
+    height: number;
+    last_used: string;
+    current: boolean;
+    in_history: boolean;
+    in_queue: boolean;
+}
+
+export interface BandwidthUsage {
+    metered: boolean;
+    day_bytes: number;
+    month_bytes: number;
+    daily_limit_bytes: number;
+    monthly_limit_bytes: number;
+    remaining_bytes: number | null;
+    max_file_bytes: number;
+    exhausted: boolean;
+}
+
+export interface WallpaperBackend {
+    id: string;
+    name: string;
+    available: boolean;
+    reason: string;
+    per_monitor: boolean;
+    selected: boolean;
+}
+
+export interface CacheUsage {
+    files: number;
+    bytes: number;
+    thumbnail_bytes: number;
+    referenced_bytes: number;
+    max_bytes: number;
+}
+
+export interface PurgeResult {
+    removed: number;
+    freed_bytes: number;
+}
+
+export interface IntegrityIssue {
+    filename: string;
+    error: string;
+    removed: boolean;
+}